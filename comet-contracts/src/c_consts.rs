@@ -5,6 +5,8 @@ pub const CPOW_PRECISION: i128 = BONE / 1e7 as i128;
 pub const EXIT_FEE: i128 = 0;
 pub const MAX_TOTAL_WEIGHT: i128 = BONE * 50; // should be 50
 pub const INIT_POOL_SUPPLY: i128 = BONE * 100; //
+pub const MIN_INIT_POOL_SUPPLY: i128 = BONE; // smallest supply finalize_with accepts
+pub const MAX_INIT_POOL_SUPPLY: i128 = BONE * 1e9 as i128; // largest supply finalize_with accepts
 pub const MIN_FEE: i128 = 10; // swap fee
 pub const MAX_FEE: i128 = 1e6 as i128; // most amount of fees you can charge
 pub const MAX_IN_RATIO: i128 = BONE / 2; //?
@@ -14,3 +16,12 @@ pub const MAX_BOUND_TOKENS: u32 = 8; // maximum of tokens can be 8
 pub const MIN_WEIGHT: i128 = BONE; //
 pub const MAX_WEIGHT: i128 = BONE * 50; // why is this repeated
 pub const MIN_BALANCE: i128 = (1e7 / 1e5) as i128; // what is the diff between balance and amount // Deposit?
+pub const MAX_REFERRAL_FEE: i128 = BONE / 20; // referral cut of a swap input can't exceed 5%
+pub const MIN_LIQUIDITY: i128 = BONE / 1000; // LP supply locked in the pool at finalize, unless set otherwise
+pub const FEE_PER_SHARE_PRECISION: i128 = 1e12 as i128; // scale of the per share swap fee accumulator
+pub const GOV_PROPOSAL_THRESHOLD: i128 = BONE / 100; // share of the supply needed to propose a fee change
+pub const GOV_QUORUM: i128 = BONE / 10; // share of the supply that has to vote on a fee change
+pub const GOV_VOTING_PERIOD: u32 = 17280; // ledgers a fee proposal is open for, about a day
+pub const GOV_EXECUTION_DELAY: u32 = 17280; // ledgers between the end of the vote and the fee change
+pub const GOV_EXECUTION_WINDOW: u32 = 51840; // ledgers a passed fee change can be executed in after the delay, about three days
+pub const MAX_MIN_LIQUIDITY: i128 = INIT_POOL_SUPPLY / 100; // locked supply can't exceed 1% of the initial supply
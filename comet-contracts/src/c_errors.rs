@@ -0,0 +1,92 @@
+use soroban_sdk::contracterror;
+
+// Every way a pool call can fail. The codes are part of the contract interface, new
+// errors are added at the end of their group and existing codes never change.
+#[contracterror]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
+#[repr(u32)]
+pub enum Error {
+    // Arithmetic, from c_num and c_math
+    AddOverflow = 1,
+    SubOverflow = 2,
+    SubUnderflow = 3,
+    MulOverflow = 4,
+    DivZero = 5,
+    DivInternal = 6,
+    CPowBaseTooLow = 7,
+    CPowBaseTooHigh = 8,
+    MathApprox = 9,
+
+    // Pool lifecycle and configuration
+    AlreadyInitialized = 20,
+    Finalized = 21,
+    NotFinalized = 22,
+    IsBound = 23,
+    NotBound = 24,
+    MinTokens = 25,
+    MaxTokens = 26,
+    TokenCount = 27,
+    MinWeight = 28,
+    MaxWeight = 29,
+    MaxTotalWeight = 30,
+    MinBalance = 31,
+    MinFee = 32,
+    MaxFee = 33,
+    MinInitSupply = 34,
+    MaxInitSupply = 35,
+    NegativeMinLiquidity = 36,
+    MaxMinLiquidity = 37,
+    MinLiquidity = 38,
+    RampEndTime = 39,
+    MissingRole = 40,
+    RescueAmount = 41,
+    RescueShares = 42,
+
+    // Swaps, joins and exits
+    SwapNotPublic = 50,
+    LimitIn = 51,
+    LimitOut = 52,
+    LimitPrice = 53,
+    BadLimitPrice = 54,
+    MaxInRatio = 55,
+    MaxOutRatio = 56,
+    MinFeeMultiplier = 57,
+    MaxFeeMultiplier = 58,
+    MinReferralFee = 59,
+    MaxReferralFee = 60,
+    NoReferrer = 61,
+    NegativeAmount = 62,
+
+    // Hooks
+    HookVeto = 70,
+    HookMinFee = 71,
+    HookMaxFee = 72,
+
+    // Swap fee governance
+    ProposalThreshold = 80,
+    NoProposal = 81,
+    VotingClosed = 82,
+    AlreadyVoted = 83,
+    NoVotingPower = 84,
+    Executed = 85,
+    Timelock = 86,
+    Quorum = 87,
+    Defeated = 88,
+    Expired = 89,
+    FeeChanged = 90,
+
+    // LP token approvals and permits
+    ExpirationInPast = 100,
+    PermitExpired = 101,
+    InvalidNonce = 102,
+    NoPermitKey = 103,
+}
+
+// Returns `err` unless `condition` holds, the fallible counterpart of `assert!`
+pub fn require(condition: bool, err: Error) -> Result<(), Error> {
+    if condition {
+        Ok(())
+    } else {
+        Err(err)
+    }
+}
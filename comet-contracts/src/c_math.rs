@@ -1,5 +1,8 @@
+use soroban_sdk::Vec;
+
 use crate::{
     c_consts::{BONE, EXIT_FEE},
+    c_errors::Error,
     c_num::{c_add, c_div, c_mul, c_pow, c_sub},
 };
 
@@ -10,7 +13,7 @@ pub fn calc_spot_price(
     token_balance_out: i128,
     token_weight_out: i128,
     swap_fee: i128,
-) -> Result<i128, &'static str> {
+) -> Result<i128, Error> {
     let numer = c_div(token_balance_in, token_weight_in)?;
     let denom = c_div(token_balance_out, token_weight_out)?;
     let ratio = c_div(numer, denom)?;
@@ -26,19 +29,15 @@ pub fn calc_token_out_given_token_in(
     token_weight_out: i128,
     token_amount_in: i128,
     swap_fee: i128,
-) -> i128 {
-    let weight_ratio = c_div(token_weight_in, token_weight_out).unwrap();
-    let adjusted_in = c_sub(BONE, swap_fee).unwrap();
-    let adjusted_in = c_mul(token_amount_in, adjusted_in).unwrap();
-    let y = c_div(
-        token_balance_in,
-        c_add(token_balance_in, adjusted_in).unwrap(),
-    )
-    .unwrap();
-    let foo = c_pow(y, weight_ratio).unwrap();
-    let bar = c_sub(BONE, foo).unwrap();
-    let token_amount_out = c_mul(token_balance_out, bar).unwrap();
-    token_amount_out
+) -> Result<i128, Error> {
+    let weight_ratio = c_div(token_weight_in, token_weight_out)?;
+    let adjusted_in = c_sub(BONE, swap_fee)?;
+    let adjusted_in = c_mul(token_amount_in, adjusted_in)?;
+    let y = c_div(token_balance_in, c_add(token_balance_in, adjusted_in)?)?;
+    let foo = c_pow(y, weight_ratio)?;
+    let bar = c_sub(BONE, foo)?;
+    let token_amount_out = c_mul(token_balance_out, bar)?;
+    Ok(token_amount_out)
 }
 
 // Calculates the amount of token A you need to have, given amount of token B you want to get
@@ -49,15 +48,15 @@ pub fn calc_token_in_given_token_out(
     token_weight_out: i128,
     token_amount_out: i128,
     swap_fee: i128,
-) -> i128 {
-    let weight_ratio = c_div(token_weight_out, token_weight_in).unwrap();
-    let diff = c_sub(token_balance_out, token_amount_out).unwrap();
-    let y = c_div(token_balance_out, diff).unwrap();
-    let mut foo = c_pow(y, weight_ratio).unwrap();
-    foo = c_sub(foo, BONE).unwrap();
-    let mut token_amount_in = c_sub(BONE, swap_fee).unwrap();
-    token_amount_in = c_div(c_mul(token_balance_in, foo).unwrap(), token_amount_in).unwrap();
-    token_amount_in
+) -> Result<i128, Error> {
+    let weight_ratio = c_div(token_weight_out, token_weight_in)?;
+    let diff = c_sub(token_balance_out, token_amount_out)?;
+    let y = c_div(token_balance_out, diff)?;
+    let mut foo = c_pow(y, weight_ratio)?;
+    foo = c_sub(foo, BONE)?;
+    let mut token_amount_in = c_sub(BONE, swap_fee)?;
+    token_amount_in = c_div(c_mul(token_balance_in, foo)?, token_amount_in)?;
+    Ok(token_amount_in)
 }
 
 // Calculates the amount of LP tokens being minted to user, given how many deposit tokens a user deposits
@@ -68,18 +67,18 @@ pub fn calc_lp_token_amount_given_token_deposits_in(
     total_weight: i128,
     token_amount_in: i128,
     swap_fee: i128,
-) -> i128 {
-    let normalized_weight = c_div(token_weight_in, total_weight).unwrap();
-    let zaz = c_mul(c_sub(BONE, normalized_weight).unwrap(), swap_fee).unwrap();
-    let token_amount_in_after_fee = c_mul(token_amount_in, c_sub(BONE, zaz).unwrap()).unwrap();
-
-    let new_token_balance_in = c_add(token_balance_in, token_amount_in_after_fee).unwrap();
-    let token_in_ratio = c_div(new_token_balance_in, token_balance_in).unwrap();
-
-    let pool_ratio = c_pow(token_in_ratio, normalized_weight).unwrap();
-    let new_pool_supply = c_mul(pool_ratio, pool_supply).unwrap();
-    let pool_amount_out = c_sub(new_pool_supply, pool_supply).unwrap();
-    pool_amount_out
+) -> Result<i128, Error> {
+    let normalized_weight = c_div(token_weight_in, total_weight)?;
+    let zaz = c_mul(c_sub(BONE, normalized_weight)?, swap_fee)?;
+    let token_amount_in_after_fee = c_mul(token_amount_in, c_sub(BONE, zaz)?)?;
+
+    let new_token_balance_in = c_add(token_balance_in, token_amount_in_after_fee)?;
+    let token_in_ratio = c_div(new_token_balance_in, token_balance_in)?;
+
+    let pool_ratio = c_pow(token_in_ratio, normalized_weight)?;
+    let new_pool_supply = c_mul(pool_ratio, pool_supply)?;
+    let pool_amount_out = c_sub(new_pool_supply, pool_supply)?;
+    Ok(pool_amount_out)
 }
 
 // If a user wants some amount of LP tokens, this is how many tokens to deposit into the pool
@@ -90,19 +89,19 @@ pub fn calc_token_deposits_in_given_lp_token_amount(
     total_weight: i128,
     pool_amount_out: i128,
     swap_fee: i128,
-) -> i128 {
-    let normalized_weight = c_div(token_weight_in, total_weight).unwrap();
-    let new_pool_supply = c_add(pool_supply, pool_amount_out).unwrap();
-    let pool_ratio = c_div(new_pool_supply, pool_supply).unwrap();
-
-    let boo = c_div(BONE, normalized_weight).unwrap();
-    let token_in_ratio = c_pow(pool_ratio, boo).unwrap();
-    let new_token_balance_in = c_mul(token_in_ratio, token_balance_in).unwrap();
-    let token_amount_in_after_fee = c_sub(new_token_balance_in, token_balance_in).unwrap();
-
-    let zar = c_mul(c_sub(BONE, normalized_weight).unwrap(), swap_fee).unwrap();
-    let token_amount_in = c_div(token_amount_in_after_fee, c_sub(BONE, zar).unwrap()).unwrap();
-    token_amount_in
+) -> Result<i128, Error> {
+    let normalized_weight = c_div(token_weight_in, total_weight)?;
+    let new_pool_supply = c_add(pool_supply, pool_amount_out)?;
+    let pool_ratio = c_div(new_pool_supply, pool_supply)?;
+
+    let boo = c_div(BONE, normalized_weight)?;
+    let token_in_ratio = c_pow(pool_ratio, boo)?;
+    let new_token_balance_in = c_mul(token_in_ratio, token_balance_in)?;
+    let token_amount_in_after_fee = c_sub(new_token_balance_in, token_balance_in)?;
+
+    let zar = c_mul(c_sub(BONE, normalized_weight)?, swap_fee)?;
+    let token_amount_in = c_div(token_amount_in_after_fee, c_sub(BONE, zar)?)?;
+    Ok(token_amount_in)
 }
 
 // Calculating the amount of LP tokens a user needs to burn, given how many deposit tokens they want to receive
@@ -113,26 +112,21 @@ pub fn calc_lp_token_amount_given_token_withdrawal_amount(
     total_weight: i128,
     token_amount_out: i128,
     swap_fee: i128,
-) -> i128 {
-    let normalized_weight = c_div(token_weight_out, total_weight).unwrap();
-    let zoo = c_sub(BONE, normalized_weight).unwrap();
-    let zar = c_mul(zoo, swap_fee).unwrap();
-    let token_amount_out_before_swap_fee =
-        c_div(token_amount_out, c_sub(BONE, zar).unwrap()).unwrap();
-
-    let new_token_balance_out = c_sub(token_balance_out, token_amount_out_before_swap_fee).unwrap();
-    let token_out_ratio = c_div(new_token_balance_out, token_balance_out).unwrap();
-
-    let pool_ratio = c_pow(token_out_ratio, normalized_weight).unwrap();
-    let new_pool_supply = c_mul(pool_ratio, pool_supply).unwrap();
-    let pool_amount_in_after_exit_fee = c_sub(pool_supply, new_pool_supply).unwrap();
-
-    let pool_amount_in = c_div(
-        pool_amount_in_after_exit_fee,
-        c_sub(BONE, EXIT_FEE).unwrap(),
-    )
-    .unwrap();
-    pool_amount_in
+) -> Result<i128, Error> {
+    let normalized_weight = c_div(token_weight_out, total_weight)?;
+    let zoo = c_sub(BONE, normalized_weight)?;
+    let zar = c_mul(zoo, swap_fee)?;
+    let token_amount_out_before_swap_fee = c_div(token_amount_out, c_sub(BONE, zar)?)?;
+
+    let new_token_balance_out = c_sub(token_balance_out, token_amount_out_before_swap_fee)?;
+    let token_out_ratio = c_div(new_token_balance_out, token_balance_out)?;
+
+    let pool_ratio = c_pow(token_out_ratio, normalized_weight)?;
+    let new_pool_supply = c_mul(pool_ratio, pool_supply)?;
+    let pool_amount_in_after_exit_fee = c_sub(pool_supply, new_pool_supply)?;
+
+    let pool_amount_in = c_div(pool_amount_in_after_exit_fee, c_sub(BONE, EXIT_FEE)?)?;
+    Ok(pool_amount_in)
 }
 
 // Calculating the amount of deposit token returned, given how many LP tokens the user wants to burn
@@ -143,22 +137,139 @@ pub fn calc_token_withdrawal_amount_given_lp_token_amount(
     total_weight: i128,
     pool_amount_in: i128,
     swap_fee: i128,
-) -> i128 {
-    let normalized_weight = c_div(token_weight_out, total_weight).unwrap();
+) -> Result<i128, Error> {
+    let normalized_weight = c_div(token_weight_out, total_weight)?;
+
+    let pool_amount_in_after_exit_fee = c_mul(pool_amount_in, c_sub(BONE, EXIT_FEE)?)?;
+    let new_pool_supply = c_sub(pool_supply, pool_amount_in_after_exit_fee)?;
+    let pool_ratio = c_div(new_pool_supply, pool_supply)?;
+
+    let token_out_ratio = c_pow(pool_ratio, c_div(BONE, normalized_weight)?)?;
+    let new_token_balance_out = c_mul(token_out_ratio, token_balance_out)?;
+
+    let token_amount_out_before_swap_fee = c_sub(token_balance_out, new_token_balance_out)?;
+
+    let zaz = c_mul(c_sub(BONE, normalized_weight)?, swap_fee)?;
+    let token_amount_out = c_mul(token_amount_out_before_swap_fee, c_sub(BONE, zaz)?)?;
+
+    Ok(token_amount_out)
+}
+
+// Calculates the amount of LP tokens minted for a deposit of any mix of the pool tokens.
+// The part of each deposit above the pool's average growth is treated as a swap and pays the swap fee
+pub fn calc_lp_token_amount_given_exact_tokens_in(
+    token_balances_in: &Vec<i128>,
+    token_weights_in: &Vec<i128>,
+    token_amounts_in: &Vec<i128>,
+    pool_supply: i128,
+    total_weight: i128,
+    swap_fee: i128,
+) -> Result<i128, Error> {
+    // Weighted average of the balance ratios, what a proportional deposit would grow the pool by
+    let mut invariant_ratio_with_fees = 0;
+    for i in 0..token_balances_in.len() {
+        let token_balance_in = token_balances_in.get(i).unwrap().unwrap();
+        let token_amount_in = token_amounts_in.get(i).unwrap().unwrap();
+        let normalized_weight = c_div(token_weights_in.get(i).unwrap().unwrap(), total_weight)?;
+        let balance_ratio = c_div(c_add(token_balance_in, token_amount_in)?, token_balance_in)?;
+        invariant_ratio_with_fees = c_add(
+            invariant_ratio_with_fees,
+            c_mul(balance_ratio, normalized_weight)?,
+        )?;
+    }
+
+    let mut invariant_ratio = BONE;
+    for i in 0..token_balances_in.len() {
+        let token_balance_in = token_balances_in.get(i).unwrap().unwrap();
+        let token_amount_in = token_amounts_in.get(i).unwrap().unwrap();
+        let normalized_weight = c_div(token_weights_in.get(i).unwrap().unwrap(), total_weight)?;
+        let balance_ratio_with_fee =
+            c_div(c_add(token_balance_in, token_amount_in)?, token_balance_in)?;
+
+        let token_amount_in_after_fee = if balance_ratio_with_fee > invariant_ratio_with_fees {
+            let non_taxable_amount =
+                c_mul(token_balance_in, c_sub(invariant_ratio_with_fees, BONE)?)?;
+            let taxable_amount = c_sub(token_amount_in, non_taxable_amount)?;
+            c_add(
+                non_taxable_amount,
+                c_mul(taxable_amount, c_sub(BONE, swap_fee)?)?,
+            )?
+        } else {
+            token_amount_in
+        };
 
-    let pool_amount_in_after_exit_fee =
-        c_mul(pool_amount_in, c_sub(BONE, EXIT_FEE).unwrap()).unwrap();
-    let new_pool_supply = c_sub(pool_supply, pool_amount_in_after_exit_fee).unwrap();
-    let pool_ratio = c_div(new_pool_supply, pool_supply).unwrap();
+        let balance_ratio = c_div(
+            c_add(token_balance_in, token_amount_in_after_fee)?,
+            token_balance_in,
+        )?;
+        invariant_ratio = c_mul(invariant_ratio, c_pow(balance_ratio, normalized_weight)?)?;
+    }
+
+    if invariant_ratio > BONE {
+        c_mul(pool_supply, c_sub(invariant_ratio, BONE)?)
+    } else {
+        Ok(0)
+    }
+}
+
+// Calculates the amount of LP tokens burned for a withdrawal of any mix of the pool tokens.
+// The part of each withdrawal above the pool's average shrink is treated as a swap and pays the swap fee
+pub fn calc_lp_token_amount_given_exact_tokens_out(
+    token_balances_out: &Vec<i128>,
+    token_weights_out: &Vec<i128>,
+    token_amounts_out: &Vec<i128>,
+    pool_supply: i128,
+    total_weight: i128,
+    swap_fee: i128,
+) -> Result<i128, Error> {
+    // Weighted average of the balance ratios, what a proportional withdrawal would shrink the pool to
+    let mut invariant_ratio_without_fees = 0;
+    for i in 0..token_balances_out.len() {
+        let token_balance_out = token_balances_out.get(i).unwrap().unwrap();
+        let token_amount_out = token_amounts_out.get(i).unwrap().unwrap();
+        let normalized_weight = c_div(token_weights_out.get(i).unwrap().unwrap(), total_weight)?;
+        let balance_ratio = c_div(
+            c_sub(token_balance_out, token_amount_out)?,
+            token_balance_out,
+        )?;
+        invariant_ratio_without_fees = c_add(
+            invariant_ratio_without_fees,
+            c_mul(balance_ratio, normalized_weight)?,
+        )?;
+    }
 
-    let token_out_ratio = c_pow(pool_ratio, c_div(BONE, normalized_weight).unwrap()).unwrap();
-    let new_token_balance_out = c_mul(token_out_ratio, token_balance_out).unwrap();
+    let mut invariant_ratio = BONE;
+    for i in 0..token_balances_out.len() {
+        let token_balance_out = token_balances_out.get(i).unwrap().unwrap();
+        let token_amount_out = token_amounts_out.get(i).unwrap().unwrap();
+        let normalized_weight = c_div(token_weights_out.get(i).unwrap().unwrap(), total_weight)?;
+        let balance_ratio_without_fee = c_div(
+            c_sub(token_balance_out, token_amount_out)?,
+            token_balance_out,
+        )?;
 
-    let token_amount_out_before_swap_fee = c_sub(token_balance_out, new_token_balance_out).unwrap();
+        let token_amount_out_before_fee =
+            if invariant_ratio_without_fees > balance_ratio_without_fee {
+                let non_taxable_amount = c_mul(
+                    token_balance_out,
+                    c_sub(BONE, invariant_ratio_without_fees)?,
+                )?;
+                let taxable_amount = c_sub(token_amount_out, non_taxable_amount)?;
+                c_add(
+                    non_taxable_amount,
+                    c_div(taxable_amount, c_sub(BONE, swap_fee)?)?,
+                )?
+            } else {
+                token_amount_out
+            };
 
-    let zaz = c_mul(c_sub(BONE, normalized_weight).unwrap(), swap_fee).unwrap();
-    let token_amount_out =
-        c_mul(token_amount_out_before_swap_fee, c_sub(BONE, zaz).unwrap()).unwrap();
+        let balance_ratio = c_div(
+            c_sub(token_balance_out, token_amount_out_before_fee)?,
+            token_balance_out,
+        )?;
+        invariant_ratio = c_mul(invariant_ratio, c_pow(balance_ratio, normalized_weight)?)?;
+    }
 
-    token_amount_out
+    let pool_amount_in_after_exit_fee = c_mul(pool_supply, c_sub(BONE, invariant_ratio)?)?;
+    c_div(pool_amount_in_after_exit_fee, c_sub(BONE, EXIT_FEE)?)
 }
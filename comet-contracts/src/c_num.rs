@@ -1,26 +1,27 @@
 use c_consts::BONE;
 
 use crate::c_consts::{self, CPOW_PRECISION, MAX_CPOW_BASE, MIN_CPOW_BASE};
+use crate::c_errors::Error;
 
-fn c_toi(a: i128) -> i128 {
-    a.checked_div(BONE).unwrap()
+fn c_toi(a: i128) -> Result<i128, Error> {
+    a.checked_div(BONE).ok_or(Error::DivInternal)
 }
 
-fn c_floor(a: i128) -> i128 {
-    c_toi(a).checked_mul(BONE).unwrap()
+fn c_floor(a: i128) -> Result<i128, Error> {
+    c_toi(a)?.checked_mul(BONE).ok_or(Error::MulOverflow)
 }
 
-// pub fn c_add(a: i128, b: i128) -> Result<i128, &'static str> {
-//     let c = a.checked_add(b).ok_or("ERR_ADD_OVERFLOW")?;
+// pub fn c_add(a: i128, b: i128) -> Result<i128, Error> {
+//     let c = a.checked_add(b).ok_or(Error::AddOverflow)?;
 //     Ok(c)
 // }
 
-pub fn c_add(a: i128, b: i128) -> Result<i128, &'static str> {
+pub fn c_add(a: i128, b: i128) -> Result<i128, Error> {
     if a < 0 && b < 0 {
-        let c = a.checked_sub(-b).ok_or("ERR_ADD_OVERFLOW")?;
+        let c = a.checked_sub(-b).ok_or(Error::AddOverflow)?;
         Ok(c)
     } else {
-        let c = a.checked_add(b).ok_or("ERR_ADD_OVERFLOW")?;
+        let c = a.checked_add(b).ok_or(Error::AddOverflow)?;
         Ok(c)
     }
 }
@@ -29,106 +30,106 @@ pub fn c_add(a: i128, b: i128) -> Result<i128, &'static str> {
 
 
 // updated function
-pub fn c_sub(a: i128, b: i128) -> Result<i128, &'static str> {
+pub fn c_sub(a: i128, b: i128) -> Result<i128, Error> {
     match a.checked_sub(b) {
         Some(result) => Ok(result),
         None => {
             if a > 0 && b < 0 {
-                Err("ERR_SUB_OVERFLOW")
+                Err(Error::SubOverflow)
             } else {
-                Err("ERR_SUB_UNDERFLOW")
+                Err(Error::SubUnderflow)
             }
         }
     }
 }
 
-pub fn c_sub_sign(a: i128, b: i128) -> (i128, bool) {
+pub fn c_sub_sign(a: i128, b: i128) -> Result<(i128, bool), Error> {
     if a >= b {
-        (a.checked_sub(b).unwrap(), false)
+        Ok((a.checked_sub(b).ok_or(Error::SubOverflow)?, false))
     } else {
-        (b.checked_sub(a).unwrap(), true)
+        Ok((b.checked_sub(a).ok_or(Error::SubOverflow)?, true))
     }
 }
 
-pub fn c_mul(a: i128, b: i128) -> Result<i128, &'static str> {
-    let c0 = a.checked_mul(b).ok_or("ERR_MUL_OVERFLOW")?;
+pub fn c_mul(a: i128, b: i128) -> Result<i128, Error> {
+    let c0 = a.checked_mul(b).ok_or(Error::MulOverflow)?;
     let c1 = c0
         .checked_add(BONE.checked_div(2).unwrap())
-        .ok_or("ERR_MUL_OVERFLOW")?;
+        .ok_or(Error::MulOverflow)?;
     if c1 < c0 {
-        return Err("ERR_MUL_OVERFLOW");
+        return Err(Error::MulOverflow);
     }
     let c2 = c1.checked_div(BONE).unwrap();
     Ok(c2)
 }
 
-pub fn c_div(a: i128, b: i128) -> Result<i128, &'static str> {
+pub fn c_div(a: i128, b: i128) -> Result<i128, Error> {
     if b == 0 {
-        return Err("ERR_DIV_ZERO");
+        return Err(Error::DivZero);
     }
-    let c0 = a.checked_mul(BONE).ok_or("ERR_DIV_INTERNAL")?;
+    let c0 = a.checked_mul(BONE).ok_or(Error::DivInternal)?;
     let c1 = c0
         .checked_add(b.checked_div(2).unwrap())
-        .ok_or("ERR_DIV_INTERNAL")?;
-    let c2 = c1.checked_div(b).ok_or("ERR_DIV_INTERNAL")?;
+        .ok_or(Error::DivInternal)?;
+    let c2 = c1.checked_div(b).ok_or(Error::DivInternal)?;
 
     Ok(c2)
 }
 
-pub fn c_powi(a: i128, n: i128) -> i128 {
+pub fn c_powi(a: i128, n: i128) -> Result<i128, Error> {
     let mut z = if n % 2 != 0 { a } else { BONE };
 
     let mut a = a;
     let mut n = n.checked_div(2).unwrap();
 
     while n != 0 {
-        a = c_mul(a, a).unwrap();
+        a = c_mul(a, a)?;
 
         if n % 2 != 0 {
-            z = c_mul(z, a).unwrap();
+            z = c_mul(z, a)?;
         }
 
         n = n.checked_div(2).unwrap();
     }
 
-    z
+    Ok(z)
 }
 
-pub fn c_pow(base: i128, exp: i128) -> Result<i128, &'static str> {
+pub fn c_pow(base: i128, exp: i128) -> Result<i128, Error> {
     if base < MIN_CPOW_BASE {
-        return Err("ERR_CPOW_BASE_TOO_LOW");
+        return Err(Error::CPowBaseTooLow);
     }
 
     if base > MAX_CPOW_BASE {
-        return Err("ERR_CPOW_BASE_TOO_HIGH");
+        return Err(Error::CPowBaseTooHigh);
     }
 
-    let whole = c_floor(exp);
+    let whole = c_floor(exp)?;
 
-    let remain = c_sub(exp, whole).unwrap();
+    let remain = c_sub(exp, whole)?;
 
-    let whole_pow = c_powi(base, c_toi(whole));
+    let whole_pow = c_powi(base, c_toi(whole)?)?;
 
     if remain == 0 {
         return Ok(whole_pow);
     }
 
-    let partial_result = c_pow_approx(base, remain, CPOW_PRECISION);
-    Ok(c_mul(whole_pow, partial_result).unwrap())
+    let partial_result = c_pow_approx(base, remain, CPOW_PRECISION)?;
+    c_mul(whole_pow, partial_result)
 }
 
-pub fn c_pow_approx(base: i128, exp: i128, precision: i128) -> i128 {
+pub fn c_pow_approx(base: i128, exp: i128, precision: i128) -> Result<i128, Error> {
     let a = exp;
-    let (x, xneg) = c_sub_sign(base, BONE);
+    let (x, xneg) = c_sub_sign(base, BONE)?;
     let mut term = BONE;
     let mut sum = term;
     let mut negative = false;
     let mut i: i128 = 1;
     while term >= precision {
-        let big_k = i.checked_mul(BONE).unwrap();
-        let (c, cneg) = c_sub_sign(a, c_sub(big_k, BONE).unwrap());
-        term = c_mul(term, c_mul(c, x).unwrap()).unwrap();
-        term = c_div(term, big_k).unwrap();
+        let big_k = i.checked_mul(BONE).ok_or(Error::MulOverflow)?;
+        let (c, cneg) = c_sub_sign(a, c_sub(big_k, BONE)?)?;
+        term = c_mul(term, c_mul(c, x)?)?;
+        term = c_div(term, big_k)?;
 
         if term == 0 {
             break;
@@ -143,13 +144,13 @@ pub fn c_pow_approx(base: i128, exp: i128, precision: i128) -> i128 {
         }
 
         if negative {
-            sum = c_sub(sum, term).unwrap();
+            sum = c_sub(sum, term)?;
         } else {
-            sum = c_add(sum, term).unwrap();
+            sum = c_add(sum, term)?;
         }
 
-        i = i.checked_add(1).unwrap();
+        i = i.checked_add(1).ok_or(Error::AddOverflow)?;
     }
 
-    sum
+    Ok(sum)
 }
@@ -1,25 +1,63 @@
 use soroban_sdk::{Address, Env};
 
-use super::storage_types::{AllowanceDataKey, DataKeyToken};
+use super::storage_types::{AllowanceDataKey, AllowanceValue, DataKeyToken};
 
-pub fn read_allowance(e: &Env, from: Address, spender: Address) -> i128 {
-    let key = DataKeyToken::Allowance(AllowanceDataKey { from, spender });
-    if let Some(allowance) = e.storage().get(&key) {
-        allowance.unwrap()
-    } else {
-        0
+// Expiration of allowances that weren't given one through `approve`
+pub const NO_EXPIRATION: u32 = u32::MAX;
+
+// Expired allowances read as a zero allowance that never expires
+pub fn read_allowance(e: &Env, from: Address, spender: Address) -> AllowanceValue {
+    let key = AllowanceDataKey { from, spender };
+    if let Some(allowance) = e
+        .storage()
+        .get::<_, AllowanceValue>(&DataKeyToken::ExpiringAllowance(key.clone()))
+    {
+        let allowance = allowance.unwrap();
+        if allowance.expiration_ledger >= e.ledger().sequence() {
+            return allowance;
+        }
+    } else if let Some(amount) = e.storage().get::<_, i128>(&DataKeyToken::Allowance(key)) {
+        // Allowances from before expiration existed keep never expiring
+        return AllowanceValue {
+            amount: amount.unwrap(),
+            expiration_ledger: NO_EXPIRATION,
+        };
+    }
+    AllowanceValue {
+        amount: 0,
+        expiration_ledger: NO_EXPIRATION,
     }
 }
 
-pub fn write_allowance(e: &Env, from: Address, spender: Address, amount: i128) {
-    let key = DataKeyToken::Allowance(AllowanceDataKey { from, spender });
-    e.storage().set(&key, &amount);
+// Also drops the legacy allowance, so it can't come back once this one expires
+pub fn write_allowance(
+    e: &Env,
+    from: Address,
+    spender: Address,
+    amount: i128,
+    expiration_ledger: u32,
+) {
+    let key = AllowanceDataKey { from, spender };
+    e.storage().remove(&DataKeyToken::Allowance(key.clone()));
+    e.storage().set(
+        &DataKeyToken::ExpiringAllowance(key),
+        &AllowanceValue {
+            amount,
+            expiration_ledger,
+        },
+    );
 }
 
 pub fn spend_allowance(e: &Env, from: Address, spender: Address, amount: i128) {
     let allowance = read_allowance(e, from.clone(), spender.clone());
-    if allowance < amount {
+    if allowance.amount < amount {
         panic!("insufficient allowance");
     }
-    write_allowance(e, from, spender, allowance - amount);
+    write_allowance(
+        e,
+        from,
+        spender,
+        allowance.amount - amount,
+        allowance.expiration_ledger,
+    );
 }
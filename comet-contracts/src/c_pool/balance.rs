@@ -1,6 +1,6 @@
 use soroban_sdk::{Address, Env};
 
-use super::storage_types::DataKeyToken;
+use super::{checkpoints, earnings, storage_types::DataKeyToken, votes};
 
 pub fn read_balance(e: &Env, addr: Address) -> i128 {
     let key = DataKeyToken::Balance(addr);
@@ -12,8 +12,9 @@ pub fn read_balance(e: &Env, addr: Address) -> i128 {
 }
 
 fn write_balance(e: &Env, addr: Address, amount: i128) {
-    let key = DataKeyToken::Balance(addr);
+    let key = DataKeyToken::Balance(addr.clone());
     e.storage().set(&key, &amount);
+    checkpoints::write_balance_checkpoint(e, addr, amount);
 }
 
 pub fn receive_balance(e: &Env, addr: Address, amount: i128) {
@@ -21,6 +22,8 @@ pub fn receive_balance(e: &Env, addr: Address, amount: i128) {
     if !is_authorized(e, addr.clone()) {
         panic!("can't receive when deauthorized");
     }
+    earnings::checkpoint(e, addr.clone(), balance);
+    votes::move_delegated_votes(e, None, Some(addr.clone()), amount);
     write_balance(e, addr, balance + amount);
 }
 // addr is account
@@ -32,6 +35,8 @@ pub fn spend_balance(e: &Env, addr: Address, amount: i128) {
     if balance < amount {
         panic!("insufficient balance");
     }
+    earnings::checkpoint(e, addr.clone(), balance);
+    votes::move_delegated_votes(e, Some(addr.clone()), None, amount);
     write_balance(e, addr, balance - amount);
 }
 
@@ -0,0 +1,78 @@
+use soroban_sdk::{Address, Env};
+
+use super::storage_types::{Checkpoint, CheckpointKey, DataKey};
+
+fn read_count(e: &Env, key: &CheckpointKey) -> u32 {
+    let key = DataKey::CheckpointCount(key.clone());
+    e.storage().get(&key).unwrap_or(Ok(0)).unwrap()
+}
+
+fn read_checkpoint(e: &Env, key: &CheckpointKey, index: u32) -> Checkpoint {
+    let key = DataKey::Checkpoint(key.clone(), index);
+    e.storage().get(&key).unwrap().unwrap()
+}
+
+// Records a new value as of the current ledger, several changes in one ledger share a checkpoint
+pub fn write_checkpoint(e: &Env, key: CheckpointKey, value: i128) {
+    let checkpoint = Checkpoint {
+        ledger: e.ledger().sequence(),
+        value,
+    };
+    let count = read_count(e, &key);
+    if count > 0 && read_checkpoint(e, &key, count - 1).ledger == checkpoint.ledger {
+        e.storage()
+            .set(&DataKey::Checkpoint(key, count - 1), &checkpoint);
+        return;
+    }
+    e.storage()
+        .set(&DataKey::Checkpoint(key.clone(), count), &checkpoint);
+    e.storage()
+        .set(&DataKey::CheckpointCount(key), &(count + 1));
+}
+
+// Latest value of a history, zero if nothing was written
+pub fn read_latest(e: &Env, key: CheckpointKey) -> i128 {
+    match read_count(e, &key) {
+        0 => 0,
+        count => read_checkpoint(e, &key, count - 1).value,
+    }
+}
+
+// Binary search for the last checkpoint at or before `ledger`, zero if there is none
+pub fn read_at(e: &Env, key: CheckpointKey, ledger: u32) -> i128 {
+    let mut low = 0;
+    let mut high = read_count(e, &key);
+    while low < high {
+        let mid = (low + high) / 2;
+        if read_checkpoint(e, &key, mid).ledger <= ledger {
+            low = mid + 1;
+        } else {
+            high = mid;
+        }
+    }
+    if low == 0 {
+        0
+    } else {
+        read_checkpoint(e, &key, low - 1).value
+    }
+}
+
+// Records an address's new LP balance as of the current ledger
+pub fn write_balance_checkpoint(e: &Env, addr: Address, balance: i128) {
+    write_checkpoint(e, CheckpointKey::Balance(addr), balance);
+}
+
+// Records the new total shares as of the current ledger
+pub fn write_supply_checkpoint(e: &Env, supply: i128) {
+    write_checkpoint(e, CheckpointKey::Supply, supply);
+}
+
+// LP balance of `addr` at the end of `ledger`
+pub fn balance_at(e: &Env, addr: Address, ledger: u32) -> i128 {
+    read_at(e, CheckpointKey::Balance(addr), ledger)
+}
+
+// Total shares at the end of `ledger`
+pub fn total_supply_at(e: &Env, ledger: u32) -> i128 {
+    read_at(e, CheckpointKey::Supply, ledger)
+}
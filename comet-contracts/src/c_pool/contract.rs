@@ -3,46 +3,63 @@ use core::ops::Add;
 use super::{
     metadata::{
         get_token_share, get_total_shares, put_total_shares, read_controller, read_factory,
-        read_record, read_swap_fee, read_tokens, read_total_weight, write_record, write_tokens,
-        write_total_weight,
+        read_fee_multiplier, read_hooks, read_legacy_events, read_max_referral_fee,
+        read_min_liquidity, read_record, read_swap_fee, read_swap_fee_ramp, read_tokens,
+        read_total_weight, write_fee_multiplier, write_legacy_events, write_max_referral_fee,
+        write_min_liquidity, write_record, write_swap_fee_ramp, write_tokens, write_total_weight,
     },
-    storage_types::{DataKey, Record},
+    storage_types::{DataKey, FeeRamp, Position, Record},
 };
 
 use super::{
     admin::{check_admin, has_administrator, write_administrator},
-    allowance::{read_allowance, spend_allowance, write_allowance},
+    allowance::{read_allowance, spend_allowance, write_allowance, NO_EXPIRATION},
     balance::{is_authorized, read_balance, receive_balance, spend_balance, write_authorization},
+    checkpoints, earnings,
     events::{
-        burn_event, clawback_event, decr_allow_event, incr_allow_event, mint_event,
-        set_admin_event, set_auth_event, transfer_event,
+        approve_event, bind_event, burn_event, clawback_event, controller_event, decr_allow_event,
+        fee_multiplier_event, fee_ramp_event, finalize_event, incr_allow_event,
+        legacy_events_event, max_referral_fee_event, min_liquidity_event, mint_event, permit_event,
+        public_swap_event, rebind_event, rescue_event, role_granted_event, role_revoked_event,
+        set_admin_event, set_auth_event, snapshot_event, swap_fee_event, transfer_event, BindEvent,
+        ControllerEvent, FeeMultiplierEvent, FeeRampEvent, FinalizeEvent, LegacyEventsEvent,
+        MaxReferralFeeEvent, MinLiquidityEvent, PublicSwapEvent, RebindEvent, SnapshotEvent,
+        SwapFeeEvent,
     },
+    governance::{self, FeeProposal},
+    hooks,
     metadata::{read_decimal, read_name, read_symbol, write_decimal, write_name, write_symbol},
+    permit::{read_nonce, use_permit, write_permit_key, PermitPayload},
+    roles::{self, require_role, write_role, Role},
+    votes,
 };
 
 use crate::{
     c_consts::{
-        EXIT_FEE, INIT_POOL_SUPPLY, MAX_BOUND_TOKENS, MAX_FEE, MAX_IN_RATIO, MAX_OUT_RATIO,
-        MAX_TOTAL_WEIGHT, MAX_WEIGHT, MIN_BALANCE, MIN_BOUND_TOKENS, MIN_FEE, MIN_WEIGHT,
+        BONE, EXIT_FEE, INIT_POOL_SUPPLY, MAX_BOUND_TOKENS, MAX_FEE, MAX_INIT_POOL_SUPPLY,
+        MAX_IN_RATIO, MAX_MIN_LIQUIDITY, MAX_OUT_RATIO, MAX_REFERRAL_FEE, MAX_TOTAL_WEIGHT,
+        MAX_WEIGHT, MIN_BALANCE, MIN_BOUND_TOKENS, MIN_FEE, MIN_INIT_POOL_SUPPLY, MIN_WEIGHT,
     },
+    c_errors::{require, Error},
     c_math::{
-        self, calc_lp_token_amount_given_token_deposits_in,
+        self, calc_lp_token_amount_given_exact_tokens_in,
+        calc_lp_token_amount_given_exact_tokens_out, calc_lp_token_amount_given_token_deposits_in,
         calc_lp_token_amount_given_token_withdrawal_amount, calc_spot_price,
         calc_token_deposits_in_given_lp_token_amount, calc_token_in_given_token_out,
         calc_token_out_given_token_in, calc_token_withdrawal_amount_given_lp_token_amount,
     },
     c_num::{c_add, c_div, c_mul, c_sub},
     c_pool::{
-        events::{ExitEvent, JoinEvent, SwapEvent},
+        events::{exit_event, join_event, swap_event, ExitEvent, JoinEvent, SwapEvent},
         metadata::{
             check_record_bound, put_token_share, read_finalize, read_public_swap, write_controller,
-            write_factory, write_finalize, write_public_swap, write_swap_fee,
+            write_factory, write_finalize, write_hooks, write_public_swap, write_swap_fee,
         },
     },
 };
 use soroban_sdk::{
     contractimpl, log, unwrap::UnwrapOptimized, vec, xdr::SurveyMessageResponseType, Address,
-    Bytes, BytesN, Env, Map, Symbol, Vec,
+    Bytes, BytesN, Env, Map, Vec,
 };
 // Token Interface
 mod token {
@@ -58,29 +75,68 @@ pub trait TokenTrait {
 
     fn allowance(e: Env, from: Address, spender: Address) -> i128;
 
-    fn incr_allow(e: Env, from: Address, spender: Address, amount: i128);
+    fn approve(
+        e: Env,
+        from: Address,
+        spender: Address,
+        amount: i128,
+        expiration_ledger: u32,
+    ) -> Result<(), Error>;
+
+    fn incr_allow(e: Env, from: Address, spender: Address, amount: i128) -> Result<(), Error>;
+
+    fn decr_allow(e: Env, from: Address, spender: Address, amount: i128) -> Result<(), Error>;
+
+    fn set_permit_key(e: Env, owner: Address, public_key: BytesN<32>);
 
-    fn decr_allow(e: Env, from: Address, spender: Address, amount: i128);
+    fn permit(
+        e: Env,
+        owner: Address,
+        spender: Address,
+        amount: i128,
+        expiry: u32,
+        signature: BytesN<64>,
+    ) -> Result<(), Error>;
+
+    fn nonce(e: Env, owner: Address) -> i128;
 
     fn balance(e: Env, id: Address) -> i128;
 
+    fn balance_at(e: Env, id: Address, ledger: u32) -> i128;
+
+    fn total_supply_at(e: Env, ledger: u32) -> i128;
+
+    fn delegate(e: Env, from: Address, to: Address);
+
+    fn delegates(e: Env, id: Address) -> Option<Address>;
+
+    fn get_votes(e: Env, id: Address) -> i128;
+
+    fn get_past_votes(e: Env, id: Address, ledger: u32) -> i128;
+
     fn spendable(e: Env, id: Address) -> i128;
 
     fn authorized(e: Env, id: Address) -> bool;
 
-    fn xfer(e: Env, from: Address, to: Address, amount: i128);
+    fn xfer(e: Env, from: Address, to: Address, amount: i128) -> Result<(), Error>;
 
-    fn xfer_from(e: Env, spender: Address, from: Address, to: Address, amount: i128);
+    fn xfer_from(
+        e: Env,
+        spender: Address,
+        from: Address,
+        to: Address,
+        amount: i128,
+    ) -> Result<(), Error>;
 
-    fn burn(e: Env, from: Address, amount: i128);
+    fn burn(e: Env, from: Address, amount: i128) -> Result<(), Error>;
 
-    fn burn_from(e: Env, spender: Address, from: Address, amount: i128);
+    fn burn_from(e: Env, spender: Address, from: Address, amount: i128) -> Result<(), Error>;
 
-    fn clawback(e: Env, admin: Address, from: Address, amount: i128);
+    fn clawback(e: Env, admin: Address, from: Address, amount: i128) -> Result<(), Error>;
 
     fn set_auth(e: Env, admin: Address, id: Address, authorize: bool);
 
-    fn mint(e: Env, admin: Address, to: Address, amount: i128);
+    fn mint(e: Env, admin: Address, to: Address, amount: i128) -> Result<(), Error>;
 
     fn set_admin(e: Env, admin: Address, new_admin: Address);
 
@@ -94,17 +150,17 @@ pub trait TokenTrait {
 
     fn get_current_tokens(e: Env) -> Vec<Address>;
 
-    fn get_final_tokens(e: Env) -> Vec<Address>;
+    fn get_final_tokens(e: Env) -> Result<Vec<Address>, Error>;
 
-    fn get_balance(e: Env, token: Address) -> i128;
+    fn get_balance(e: Env, token: Address) -> Result<i128, Error>;
 
     fn get_total_denormalized_weight(e: Env) -> i128;
 
-    fn get_denormalized_weight(e: Env, token: Address) -> i128;
+    fn get_denormalized_weight(e: Env, token: Address) -> Result<i128, Error>;
 
-    fn get_normalized_weight(e: Env, token: Address) -> i128;
+    fn get_normalized_weight(e: Env, token: Address) -> Result<i128, Error>;
 
-    fn get_spot_price(e: Env, token_in: Address, token_out: Address) -> i128;
+    fn get_spot_price(e: Env, token_in: Address, token_out: Address) -> Result<i128, Error>;
 
     fn get_swap_fee(e: Env) -> i128;
 
@@ -112,31 +168,133 @@ pub trait TokenTrait {
 
     fn share_id(e: Env) -> BytesN<32>;
 
+    fn get_position(e: Env, address: Address, numeraire: Address) -> Result<Position, Error>;
+
+    fn get_fees_earned(e: Env, lp: Address) -> Vec<i128>;
+
     fn is_public_swap(e: Env) -> bool;
 
     fn is_finalized(e: Env) -> bool;
 
-    fn get_spot_price_sans_fee(e: Env, token_in: Address, token_out: Address) -> i128;
+    fn get_spot_price_sans_fee(
+        e: Env,
+        token_in: Address,
+        token_out: Address,
+    ) -> Result<i128, Error>;
+
+    fn set_swap_fee(e: Env, fee: i128, caller: Address) -> Result<(), Error>;
+
+    fn ramp_swap_fee(e: Env, caller: Address, target_fee: i128, end_time: u64)
+        -> Result<(), Error>;
+
+    fn get_swap_fee_ramp(e: Env) -> Option<FeeRamp>;
+
+    fn propose_swap_fee(e: Env, proposer: Address, fee: i128) -> Result<u32, Error>;
+
+    fn vote_swap_fee(e: Env, voter: Address, proposal_id: u32, support: bool) -> Result<(), Error>;
+
+    fn execute_swap_fee(e: Env, proposal_id: u32) -> Result<(), Error>;
+
+    fn get_fee_proposal(e: Env, proposal_id: u32) -> Result<FeeProposal, Error>;
+
+    fn set_fee_multiplier(
+        e: Env,
+        caller: Address,
+        user: Address,
+        multiplier: i128,
+    ) -> Result<(), Error>;
+
+    fn get_effective_fee(e: Env, user: Address) -> Result<i128, Error>;
+
+    fn set_max_referral_fee(e: Env, caller: Address, fee: i128) -> Result<(), Error>;
+
+    fn get_max_referral_fee(e: Env) -> i128;
+
+    fn set_min_liquidity(e: Env, caller: Address, amount: i128) -> Result<(), Error>;
 
-    fn set_swap_fee(e: Env, fee: i128, caller: Address);
+    fn get_min_liquidity(e: Env) -> i128;
 
-    fn set_controller(e: Env, caller: Address, manager: Address);
+    fn set_controller(e: Env, caller: Address, manager: Address) -> Result<(), Error>;
 
-    fn set_public_swap(e: Env, caller: Address, val: bool);
+    fn grant_role(e: Env, caller: Address, role: Role, account: Address) -> Result<(), Error>;
 
-    fn init(e: Env, factory: Address, controller: Address, token_wasm_hash: BytesN<32>);
+    fn revoke_role(e: Env, caller: Address, role: Role, account: Address) -> Result<(), Error>;
+
+    fn renounce_role(e: Env, account: Address, role: Role);
+
+    fn has_role(e: Env, role: Role, account: Address) -> bool;
+
+    fn rescue_token(
+        e: Env,
+        caller: Address,
+        token: Address,
+        to: Address,
+        amount: i128,
+    ) -> Result<(), Error>;
+
+    fn set_public_swap(e: Env, caller: Address, val: bool) -> Result<(), Error>;
+
+    fn set_legacy_events(e: Env, caller: Address, val: bool) -> Result<(), Error>;
+
+    fn get_legacy_events(e: Env) -> bool;
+
+    fn init(
+        e: Env,
+        factory: Address,
+        controller: Address,
+        token_wasm_hash: BytesN<32>,
+        hooks: Option<Address>,
+    ) -> Result<(), Error>;
+
+    fn get_hooks(e: Env) -> Option<Address>;
 
     fn get_controller(e: Env) -> Address;
 
-    fn bind(e: Env, token: Address, balance: i128, denorm: i128, admin: Address);
+    fn bind(
+        e: Env,
+        token: Address,
+        balance: i128,
+        denorm: i128,
+        admin: Address,
+    ) -> Result<(), Error>;
+
+    fn rebind(
+        e: Env,
+        token: Address,
+        balance: i128,
+        denorm: i128,
+        admin: Address,
+    ) -> Result<(), Error>;
+
+    fn finalize(e: Env, caller: Address) -> Result<(), Error>;
 
-    fn rebind(e: Env, token: Address, balance: i128, denorm: i128, admin: Address);
+    fn finalize_with(
+        e: Env,
+        supply: i128,
+        recipient: Address,
+        caller: Address,
+    ) -> Result<(), Error>;
 
-    fn finalize(e: Env);
+    fn join_pool(
+        e: Env,
+        pool_amount_out: i128,
+        max_amounts_in: Vec<i128>,
+        user: Address,
+    ) -> Result<(), Error>;
 
-    fn join_pool(e: Env, pool_amount_out: i128, max_amounts_in: Vec<i128>, user: Address);
+    fn join_pool_max(
+        e: Env,
+        max_amounts_in: Vec<i128>,
+        min_pool_amount_out: i128,
+        user: Address,
+    ) -> Result<(i128, Vec<i128>), Error>;
 
-    fn exit_pool(e: Env, pool_amount_in: i128, min_amounts_out: Vec<i128>, user: Address);
+    fn exit_pool(
+        e: Env,
+        pool_amount_in: i128,
+        min_amounts_out: Vec<i128>,
+        user: Address,
+    ) -> Result<(), Error>;
 
     fn swap_exact_amount_in(
         e: Env,
@@ -146,7 +304,9 @@ pub trait TokenTrait {
         min_amount_out: i128,
         max_price: i128,
         user: Address,
-    ) -> (i128, i128);
+        referrer: Option<Address>,
+        referral_fee: i128,
+    ) -> Result<(i128, i128), Error>;
 
     fn swap_exact_amount_out(
         e: Env,
@@ -156,7 +316,9 @@ pub trait TokenTrait {
         token_amount_out: i128,
         max_price: i128,
         user: Address,
-    ) -> (i128, i128);
+        referrer: Option<Address>,
+        referral_fee: i128,
+    ) -> Result<(i128, i128), Error>;
 
     fn dep_lp_tokn_amt_out_get_tokn_in(
         e: Env,
@@ -164,7 +326,7 @@ pub trait TokenTrait {
         pool_amount_out: i128,
         max_amount_in: i128,
         user: Address,
-    ) -> i128;
+    ) -> Result<i128, Error>;
 
     fn wdr_tokn_amt_in_get_lp_tokns_out(
         e: Env,
@@ -172,7 +334,7 @@ pub trait TokenTrait {
         pool_amount_in: i128,
         min_amount_out: i128,
         user: Address,
-    ) -> i128;
+    ) -> Result<i128, Error>;
 
     fn wdr_tokn_amt_out_get_lp_tokns_in(
         e: Env,
@@ -180,7 +342,7 @@ pub trait TokenTrait {
         token_amount_out: i128,
         max_pool_amount_in: i128,
         user: Address,
-    ) -> i128;
+    ) -> Result<i128, Error>;
 
     fn dep_tokn_amt_in_get_lp_tokns_out(
         e: Env,
@@ -188,16 +350,61 @@ pub trait TokenTrait {
         token_amount_in: i128,
         min_pool_amount_out: i128,
         user: Address,
-    ) -> i128;
+    ) -> Result<i128, Error>;
+
+    fn join_exact_tokens_in(
+        e: Env,
+        amounts_in: Vec<i128>,
+        min_pool_amount_out: i128,
+        user: Address,
+    ) -> Result<i128, Error>;
+
+    fn zap_in(
+        e: Env,
+        token_in: Address,
+        token_amount_in: i128,
+        min_pool_amount_out: i128,
+        user: Address,
+    ) -> Result<i128, Error>;
+
+    fn quote_zap_in(
+        e: Env,
+        token_in: Address,
+        token_amount_in: i128,
+        user: Address,
+    ) -> Result<i128, Error>;
+
+    fn quote_join_exact_tokens_in(e: Env, amounts_in: Vec<i128>) -> Result<i128, Error>;
+
+    fn exit_exact_tokens_out(
+        e: Env,
+        amounts_out: Vec<i128>,
+        max_pool_amount_in: i128,
+        user: Address,
+    ) -> Result<i128, Error>;
+
+    fn quote_exit_exact_tokens_out(e: Env, amounts_out: Vec<i128>) -> Result<i128, Error>;
 }
 
 #[contractimpl]
 impl TokenTrait for CometPoolContract {
-    fn init(e: Env, factory: Address, controller: Address, token_wasm_hash: BytesN<32>) {
-        assert!(!e.storage().has(&DataKey::Factory), "already initialized");
+    fn init(
+        e: Env,
+        factory: Address,
+        controller: Address,
+        token_wasm_hash: BytesN<32>,
+        hooks: Option<Address>,
+    ) -> Result<(), Error> {
+        require(
+            !e.storage().has(&DataKey::Factory),
+            Error::AlreadyInitialized,
+        )?;
 
         write_factory(&e, factory);
         write_controller(&e, controller);
+        if let Some(hooks) = hooks {
+            write_hooks(&e, hooks);
+        }
         let val: &Address = &e.current_contract_address();
         // Another Contract on Soroban
         let name = Bytes::from_slice(&e, b"Comet Pool Token");
@@ -210,18 +417,30 @@ impl TokenTrait for CometPoolContract {
         write_public_swap(&e, false);
 
         Self::initialize(e, val.clone(), 7u32, name, symbol);
+
+        Ok(())
     }
 
     fn get_controller(e: Env) -> Address {
         read_controller(&e)
     }
 
-    fn bind(e: Env, token: Address, balance: i128, denorm: i128, admin: Address) {
-        assert!(!read_finalize(&e), "ERR_FINALIZED");
-        assert!(!check_record_bound(&e, token.clone()), "ERR_IS_BOUND");
-        let controller = read_controller(&e);
-        controller.require_auth();
-        assert!(read_tokens(&e).len() < MAX_BOUND_TOKENS, "ERR_MAX_TOKENS");
+    fn get_hooks(e: Env) -> Option<Address> {
+        read_hooks(&e)
+    }
+
+    fn bind(
+        e: Env,
+        token: Address,
+        balance: i128,
+        denorm: i128,
+        admin: Address,
+    ) -> Result<(), Error> {
+        require(!read_finalize(&e), Error::Finalized)?;
+        require(!check_record_bound(&e, token.clone()), Error::IsBound)?;
+        require_role(&e, &admin, Role::TokenBinder)?;
+        admin.require_auth();
+        require(read_tokens(&e).len() < MAX_BOUND_TOKENS, Error::MaxTokens)?;
         let key = DataKey::AllTokenVec;
         let key_rec = DataKey::AllRecordData;
 
@@ -246,119 +465,114 @@ impl TokenTrait for CometPoolContract {
         tokens_arr.push_back(token.clone());
         write_tokens(&e, tokens_arr);
 
-        Self::rebind(e, token, balance, denorm, admin);
-    }
-
-    fn rebind(e: Env, token: Address, balance: i128, denorm: i128, admin: Address) {
-        assert!(!read_finalize(&e), "ERR_FINALIZED");
-        let controller = read_controller(&e);
-        controller.require_auth();
-        assert!(read_tokens(&e).len() < MAX_BOUND_TOKENS, "ERR_MAX_TOKENS");
-        assert!(check_record_bound(&e, token.clone()), "ERR_NOT_BOUND");
-        assert!(denorm >= MIN_WEIGHT, "ERR_MIN_WEIGHT");
-        assert!(denorm <= MAX_WEIGHT, "ERR_MAX_WEIGHT");
-        assert!(balance >= MIN_BALANCE, "ERR_MIN_BALANCE");
-
-        let mut record_map: Map<Address, Record> = read_record(&e);
-        let mut record = record_map.get(token.clone()).unwrap().unwrap();
-        let old_weight = record.denorm;
-        let mut total_weight = read_total_weight(&e);
-
-        if denorm > old_weight {
-            total_weight = c_add(total_weight, c_sub(denorm, old_weight).unwrap()).unwrap();
-            write_total_weight(&e, total_weight);
-            if total_weight > MAX_TOTAL_WEIGHT {
-                panic!("ERR_MAX_TOTAL_WEIGHT");
-            }
-        } else if denorm < old_weight {
-            total_weight = c_sub(total_weight, c_sub(old_weight, denorm).unwrap()).unwrap();
-            write_total_weight(&e, total_weight);
-        }
-
-        record.denorm = denorm;
+        rebind_token(&e, token.clone(), balance, denorm, admin.clone())?;
+        bind_event(
+            &e,
+            BindEvent {
+                caller: admin,
+                token: token.clone(),
+                balance,
+                denorm,
+            },
+        );
+        publish_snapshot(&e, &vec![&e, token])?;
 
-        let old_balance = record.balance;
-        record.balance = balance;
+        Ok(())
+    }
 
-        if balance > old_balance {
-            pull_underlying(&e, &token, admin, c_sub(balance, old_balance).unwrap());
-        } else if balance < old_balance {
-            let token_balance_withdrawn = c_sub(old_balance, balance).unwrap();
-            let token_exit_fee = c_mul(token_balance_withdrawn, 0).unwrap();
-            push_underlying(
-                &e,
-                &token,
-                admin,
-                c_sub(token_balance_withdrawn, token_exit_fee).unwrap(),
-            );
-            let factory = read_factory(&e);
-            push_underlying(&e, &token, factory, token_exit_fee)
-        }
+    fn rebind(
+        e: Env,
+        token: Address,
+        balance: i128,
+        denorm: i128,
+        admin: Address,
+    ) -> Result<(), Error> {
+        require(!read_finalize(&e), Error::Finalized)?;
+        require_role(&e, &admin, Role::WeightManager)?;
+        admin.require_auth();
+        require(check_record_bound(&e, token.clone()), Error::NotBound)?;
+        let old_record = read_record(&e).get(token.clone()).unwrap().unwrap();
+        rebind_token(&e, token.clone(), balance, denorm, admin.clone())?;
+        rebind_event(
+            &e,
+            RebindEvent {
+                caller: admin,
+                token: token.clone(),
+                old_balance: old_record.balance,
+                new_balance: balance,
+                old_denorm: old_record.denorm,
+                new_denorm: denorm,
+            },
+        );
+        publish_snapshot(&e, &vec![&e, token])?;
 
-        record_map.set(token, record);
-        write_record(&e, record_map);
+        Ok(())
     }
 
-    fn finalize(e: Env) {
-        assert!(!read_finalize(&e), "ERR_FINALIZED");
-        assert!(read_tokens(&e).len() > MIN_BOUND_TOKENS, "ERR_MIN_TOKENS");
+    fn finalize(e: Env, caller: Address) -> Result<(), Error> {
         let controller = read_controller(&e);
+        finalize_pool(e, caller, INIT_POOL_SUPPLY, controller)
+    }
 
-        controller.require_auth();
-        write_finalize(&e, true);
-        write_public_swap(&e, true);
-        mint_shares(e, controller, INIT_POOL_SUPPLY);
+    // Same as finalize, with the initial LP supply and the address receiving it chosen by an admin
+    fn finalize_with(
+        e: Env,
+        supply: i128,
+        recipient: Address,
+        caller: Address,
+    ) -> Result<(), Error> {
+        require(supply >= MIN_INIT_POOL_SUPPLY, Error::MinInitSupply)?;
+        require(supply <= MAX_INIT_POOL_SUPPLY, Error::MaxInitSupply)?;
+        finalize_pool(e, caller, supply, recipient)
     }
 
-    fn join_pool(e: Env, pool_amount_out: i128, max_amounts_in: Vec<i128>, user: Address) {
-        assert!(read_finalize(&e), "ERR_NOT_FINALIZED");
+    fn join_pool(
+        e: Env,
+        pool_amount_out: i128,
+        max_amounts_in: Vec<i128>,
+        user: Address,
+    ) -> Result<(), Error> {
+        require(read_finalize(&e), Error::NotFinalized)?;
 
         user.require_auth();
 
-        let pool_total = get_total_shares(&e);
-        let ratio = c_div(pool_amount_out, pool_total).unwrap();
+        join_proportional(&e, pool_amount_out, &max_amounts_in, user)?;
 
-        if ratio == 0 {
-            panic!("ERR_MATH_APPROX")
-        }
-        let tokens = read_tokens(&e);
-        let mut records = read_record(&e);
-        for i in 0..tokens.len() {
-            let t = tokens.get(i).unwrap().unwrap();
-            let mut rec = records.get(t.clone()).unwrap().unwrap();
-            let token_amount_in = c_mul(ratio, rec.balance).unwrap();
-            if token_amount_in == 0 {
-                panic!("ERR_MATH_APPROX")
-            }
+        Ok(())
+    }
 
-            if token_amount_in > max_amounts_in.get(i).unwrap().unwrap() {
-                panic!("ERR_LIMIT_IN")
-            }
-            rec.balance = c_add(rec.balance, token_amount_in).unwrap();
-            records.set(t.clone(), rec);
-            // emit LOG_JOIN(msg.sender, t, tokenAmountIn);
-            let event: JoinEvent = JoinEvent {
-                caller: user.clone(),
-                token_in: t.clone(),
-                token_amount_in: token_amount_in,
-            };
-            e.events()
-                .publish((Symbol::short("LOG"), Symbol::short("JOIN")), event);
-            pull_underlying(&e, &t, user.clone(), token_amount_in);
-        }
+    fn join_pool_max(
+        e: Env,
+        max_amounts_in: Vec<i128>,
+        min_pool_amount_out: i128,
+        user: Address,
+    ) -> Result<(i128, Vec<i128>), Error> {
+        require(read_finalize(&e), Error::NotFinalized)?;
 
-        write_record(&e, records);
-        mint_shares(e, user, pool_amount_out);
+        user.require_auth();
+
+        let pool_amount_out = calc_max_pool_amount_out(&e, &read_record(&e), &max_amounts_in)?;
+        require(pool_amount_out >= min_pool_amount_out, Error::LimitOut)?;
+        let amounts_in = join_proportional(&e, pool_amount_out, &max_amounts_in, user)?;
+        Ok((pool_amount_out, amounts_in))
     }
 
-    fn exit_pool(e: Env, pool_amount_in: i128, min_amounts_out: Vec<i128>, user: Address) {
+    fn exit_pool(
+        e: Env,
+        pool_amount_in: i128,
+        min_amounts_out: Vec<i128>,
+        user: Address,
+    ) -> Result<(), Error> {
         user.require_auth();
-        assert!(read_finalize(&e), "ERR_NOT_FINALIZED");
+        require(read_finalize(&e), Error::NotFinalized)?;
+        let tokens = read_tokens(&e);
+        hooks::before_exit(&e, &user, &tokens, pool_amount_in, read_swap_fee(&e))?;
+
         let pool_total = get_total_shares(&e);
-        let exit_fee = c_mul(pool_amount_in, EXIT_FEE).unwrap();
-        let pai_after_exit_fee = c_sub(pool_amount_in, EXIT_FEE).unwrap();
-        let ratio = c_div(pai_after_exit_fee, pool_total).unwrap();
-        assert!(ratio != 0, "ERR_MATH_APPROX");
+        let exit_fee = c_mul(pool_amount_in, EXIT_FEE)?;
+        let pai_after_exit_fee = c_sub(pool_amount_in, EXIT_FEE)?;
+        let ratio = c_div(pai_after_exit_fee, pool_total)?;
+        require(ratio != 0, Error::MathApprox)?;
 
         pull_shares(&e, user.clone(), pool_amount_in);
         let share_contract_id = get_token_share(&e);
@@ -367,31 +581,39 @@ impl TokenTrait for CometPoolContract {
             Address::from_contract_id(&e, &share_contract_id),
             EXIT_FEE,
         );
-        burn_shares(&e, pai_after_exit_fee);
-        let tokens = read_tokens(&e);
+        burn_shares(&e, pai_after_exit_fee)?;
         let mut records = read_record(&e);
+        let mut amounts_out: Vec<i128> = vec![&e];
         for i in 0..tokens.len() {
             let t = tokens.get(i).unwrap().unwrap();
             let mut rec = records.get(t.clone()).unwrap().unwrap();
-            let token_amount_out = c_mul(ratio, rec.balance).unwrap();
-            assert!(token_amount_out != 0, "ERR_MATH_APPROX");
-            assert!(
+            let token_amount_out = c_mul(ratio, rec.balance)?;
+            require(token_amount_out != 0, Error::MathApprox)?;
+            require(
                 token_amount_out >= min_amounts_out.get(i).unwrap().unwrap(),
-                "ERR_LIMIT_OUT"
-            );
-            rec.balance = c_sub(rec.balance, token_amount_out).unwrap();
+                Error::LimitOut,
+            )?;
+            rec.balance = c_sub(rec.balance, token_amount_out)?;
             records.set(t.clone(), rec);
-            let event: ExitEvent = ExitEvent {
-                caller: user.clone(),
-                token_out: t.clone(),
-                token_amount_out: token_amount_out,
-            };
-            e.events()
-                .publish((Symbol::short("LOG"), Symbol::short("EXIT")), event);
-            push_underlying(&e, &t, user.clone(), token_amount_out)
+            exit_event(
+                &e,
+                ExitEvent {
+                    caller: user.clone(),
+                    token_out: t.clone(),
+                    token_amount_out: token_amount_out,
+                    pool_amount_in,
+                },
+            );
+            push_underlying(&e, &t, user.clone(), token_amount_out);
+            amounts_out.push_back(token_amount_out);
         }
 
         write_record(&e, records);
+        // A proportional exit leaves every price where it was
+        publish_snapshot(&e, &vec![&e])?;
+        hooks::after_exit(&e, &user, &tokens, &amounts_out, pool_amount_in);
+
+        Ok(())
     }
 
     fn swap_exact_amount_in(
@@ -402,77 +624,106 @@ impl TokenTrait for CometPoolContract {
         min_amount_out: i128,
         max_price: i128,
         user: Address,
-    ) -> (i128, i128) {
-        assert!(read_public_swap(&e), "ERR_SWAP_NOT_PUBLIC");
-        assert!(check_record_bound(&e, token_in.clone()), "ERR_NOT_BOUND");
-        assert!(check_record_bound(&e, token_out.clone()), "ERR_NOT_BOUND");
+        referrer: Option<Address>,
+        referral_fee: i128,
+    ) -> Result<(i128, i128), Error> {
+        require(read_public_swap(&e), Error::SwapNotPublic)?;
+        require(check_record_bound(&e, token_in.clone()), Error::NotBound)?;
+        require(check_record_bound(&e, token_out.clone()), Error::NotBound)?;
 
         user.require_auth();
+        check_referral(&e, &referrer, referral_fee)?;
+        let base_fee = hooks::before_swap(
+            &e,
+            &user,
+            &token_in,
+            &token_out,
+            token_amount_in,
+            true,
+            read_swap_fee(&e),
+        )?;
+        let swap_fee = effective_swap_fee(&e, user.clone(), base_fee)?;
         let mut in_record = read_record(&e).get(token_in.clone()).unwrap().unwrap();
         let mut out_record = read_record(&e).get(token_out.clone()).unwrap().unwrap();
-        assert!(
-            token_amount_in <= c_mul(in_record.balance, MAX_IN_RATIO).unwrap(),
-            "ERR_MAX_IN_RATIO"
-        );
+
+        // The referral fee is taken first, the pool only trades what is left
+        let referral_amount = c_mul(token_amount_in, referral_fee)?;
+        let token_amount_in = c_sub(token_amount_in, referral_amount)?;
+        require(
+            token_amount_in <= c_mul(in_record.balance, MAX_IN_RATIO)?,
+            Error::MaxInRatio,
+        )?;
 
         let spot_price_before = calc_spot_price(
             in_record.balance,
             in_record.denorm,
             out_record.balance,
             out_record.denorm,
-            read_swap_fee(&e),
-        )
-        .unwrap();
-        assert!(spot_price_before <= max_price, "ERR_BAD_LIMIT_PRICE");
+            swap_fee,
+        )?;
+        require(spot_price_before <= max_price, Error::BadLimitPrice)?;
         let token_amount_out = calc_token_out_given_token_in(
             in_record.balance,
             in_record.denorm,
             out_record.balance,
             out_record.denorm,
             token_amount_in,
-            read_swap_fee(&e),
-        );
-        assert!(token_amount_out >= min_amount_out, "ERR_LIMIT_OUT");
+            swap_fee,
+        )?;
+        require(token_amount_out >= min_amount_out, Error::LimitOut)?;
 
-        in_record.balance = c_add(in_record.balance, token_amount_in).unwrap();
-        out_record.balance = c_sub(out_record.balance, token_amount_out).unwrap();
+        in_record.balance = c_add(in_record.balance, token_amount_in)?;
+        out_record.balance = c_sub(out_record.balance, token_amount_out)?;
 
         let spot_price_after = calc_spot_price(
             in_record.balance,
             in_record.denorm,
             out_record.balance,
             out_record.denorm,
-            read_swap_fee(&e),
-        )
-        .unwrap();
+            swap_fee,
+        )?;
 
-        assert!(spot_price_after >= spot_price_before, "ERR_MATH_APPROX");
-        assert!(spot_price_after <= max_price, "ERR_LIMIT_PRICE");
-        assert!(
-            spot_price_before <= c_div(token_amount_in, token_amount_out).unwrap(),
-            "ERR_MATH_APPROX"
-        );
+        require(spot_price_after >= spot_price_before, Error::MathApprox)?;
+        require(spot_price_after <= max_price, Error::LimitPrice)?;
+        require(
+            spot_price_before <= c_div(token_amount_in, token_amount_out)?,
+            Error::MathApprox,
+        )?;
 
-        let event: SwapEvent = SwapEvent {
-            caller: user.clone(),
-            token_in: token_in.clone(),
-            token_out: token_out.clone(),
-            token_amount_in: token_amount_in,
-            token_amount_out: token_amount_out,
-        };
-        e.events()
-            .publish((Symbol::short("LOG"), Symbol::short("SWAP")), event);
+        swap_event(
+            &e,
+            SwapEvent {
+                caller: user.clone(),
+                token_in: token_in.clone(),
+                token_out: token_out.clone(),
+                token_amount_in: token_amount_in,
+                token_amount_out: token_amount_out,
+                referrer: referrer.clone(),
+                referral_amount,
+            },
+        );
 
         pull_underlying(&e, &token_in, user.clone(), token_amount_in);
-        push_underlying(&e, &token_out, user, token_amount_out);
+        push_underlying(&e, &token_out, user.clone(), token_amount_out);
+        pay_referral(&e, &token_in, user.clone(), referrer, referral_amount);
 
         let mut record_map = read_record(&e);
-        record_map.set(token_in, in_record);
-        record_map.set(token_out, out_record);
+        record_map.set(token_in.clone(), in_record);
+        record_map.set(token_out.clone(), out_record);
 
         write_record(&e, record_map);
+        earnings::accrue_fee(&e, token_in.clone(), c_mul(token_amount_in, swap_fee)?)?;
+        publish_snapshot(&e, &vec![&e, token_in.clone(), token_out.clone()])?;
+        hooks::after_swap(
+            &e,
+            &user,
+            &token_in,
+            &token_out,
+            token_amount_in,
+            token_amount_out,
+        );
 
-        (token_amount_out, spot_price_after)
+        Ok((token_amount_out, spot_price_after))
     }
 
     fn swap_exact_amount_out(
@@ -483,78 +734,106 @@ impl TokenTrait for CometPoolContract {
         token_amount_out: i128,
         max_price: i128,
         user: Address,
-    ) -> (i128, i128) {
-        assert!(check_record_bound(&e, token_in.clone()), "ERR_NOT_BOUND");
-        assert!(check_record_bound(&e, token_out.clone()), "ERR_NOT_BOUND");
-        assert!(read_public_swap(&e), "ERR_SWAP_NOT_PUBLIC");
+        referrer: Option<Address>,
+        referral_fee: i128,
+    ) -> Result<(i128, i128), Error> {
+        require(check_record_bound(&e, token_in.clone()), Error::NotBound)?;
+        require(check_record_bound(&e, token_out.clone()), Error::NotBound)?;
+        require(read_public_swap(&e), Error::SwapNotPublic)?;
 
         user.require_auth();
+        check_referral(&e, &referrer, referral_fee)?;
+        let base_fee = hooks::before_swap(
+            &e,
+            &user,
+            &token_in,
+            &token_out,
+            token_amount_out,
+            false,
+            read_swap_fee(&e),
+        )?;
+        let swap_fee = effective_swap_fee(&e, user.clone(), base_fee)?;
         let mut in_record = read_record(&e).get(token_in.clone()).unwrap().unwrap();
         let mut out_record = read_record(&e).get(token_out.clone()).unwrap().unwrap();
-        assert!(
-            token_amount_out <= c_mul(out_record.balance, MAX_OUT_RATIO).unwrap(),
-            "ERR_MAX_IN_RATIO"
-        );
+        require(
+            token_amount_out <= c_mul(out_record.balance, MAX_OUT_RATIO)?,
+            Error::MaxOutRatio,
+        )?;
 
         let spot_price_before = calc_spot_price(
             in_record.balance,
             in_record.denorm,
             out_record.balance,
             out_record.denorm,
-            read_swap_fee(&e),
-        )
-        .unwrap();
-        assert!(spot_price_before <= max_price, "ERR_BAD_LIMIT_PRICE");
+            swap_fee,
+        )?;
+        require(spot_price_before <= max_price, Error::BadLimitPrice)?;
         let token_amount_in = calc_token_in_given_token_out(
             in_record.balance,
             in_record.denorm,
             out_record.balance,
             out_record.denorm,
             token_amount_out,
-            read_swap_fee(&e),
-        );
+            swap_fee,
+        )?;
 
-        assert!(token_amount_in <= max_amount_in, "ERR_LIMIT_IN");
+        // Gross the input up so the referral fee comes on top of what the pool needs
+        let user_amount_in = c_div(token_amount_in, c_sub(BONE, referral_fee)?)?;
+        let referral_amount = c_sub(user_amount_in, token_amount_in)?;
+        require(user_amount_in <= max_amount_in, Error::LimitIn)?;
 
-        in_record.balance = c_add(in_record.balance, token_amount_in).unwrap();
-        out_record.balance = c_sub(out_record.balance, token_amount_out).unwrap();
+        in_record.balance = c_add(in_record.balance, token_amount_in)?;
+        out_record.balance = c_sub(out_record.balance, token_amount_out)?;
 
         let spot_price_after = calc_spot_price(
             in_record.balance,
             in_record.denorm,
             out_record.balance,
             out_record.denorm,
-            read_swap_fee(&e),
-        )
-        .unwrap();
+            swap_fee,
+        )?;
 
-        assert!(spot_price_after >= spot_price_before, "ERR_MATH_APPROX");
-        assert!(spot_price_after <= max_price, "ERR_LIMIT_PRICE");
-        assert!(
-            spot_price_before <= c_div(token_amount_in, token_amount_out).unwrap(),
-            "ERR_MATH_APPROX"
-        );
+        require(spot_price_after >= spot_price_before, Error::MathApprox)?;
+        require(spot_price_after <= max_price, Error::LimitPrice)?;
+        require(
+            spot_price_before <= c_div(token_amount_in, token_amount_out)?,
+            Error::MathApprox,
+        )?;
 
-        let event: SwapEvent = SwapEvent {
-            caller: user.clone(),
-            token_in: token_in.clone(),
-            token_out: token_out.clone(),
-            token_amount_in: token_amount_in,
-            token_amount_out: token_amount_out,
-        };
-        e.events()
-            .publish((Symbol::short("LOG"), Symbol::short("SWAP")), event);
+        swap_event(
+            &e,
+            SwapEvent {
+                caller: user.clone(),
+                token_in: token_in.clone(),
+                token_out: token_out.clone(),
+                token_amount_in: token_amount_in,
+                token_amount_out: token_amount_out,
+                referrer: referrer.clone(),
+                referral_amount,
+            },
+        );
 
         pull_underlying(&e, &token_in, user.clone(), token_amount_in);
-        push_underlying(&e, &token_out, user, token_amount_out);
+        push_underlying(&e, &token_out, user.clone(), token_amount_out);
+        pay_referral(&e, &token_in, user.clone(), referrer, referral_amount);
 
         let mut record_map = read_record(&e);
-        record_map.set(token_in, in_record);
-        record_map.set(token_out, out_record);
+        record_map.set(token_in.clone(), in_record);
+        record_map.set(token_out.clone(), out_record);
 
         write_record(&e, record_map);
+        earnings::accrue_fee(&e, token_in.clone(), c_mul(token_amount_in, swap_fee)?)?;
+        publish_snapshot(&e, &vec![&e, token_in.clone(), token_out.clone()])?;
+        hooks::after_swap(
+            &e,
+            &user,
+            &token_in,
+            &token_out,
+            token_amount_in,
+            token_amount_out,
+        );
 
-        (token_amount_in, spot_price_after)
+        Ok((user_amount_in, spot_price_after))
     }
 
     fn dep_tokn_amt_in_get_lp_tokns_out(
@@ -563,10 +842,10 @@ impl TokenTrait for CometPoolContract {
         token_amount_in: i128,
         min_pool_amount_out: i128,
         user: Address,
-    ) -> i128 {
-        assert!(read_finalize(&e), "ERR_NOT_FINALIZED");
-        assert!(check_record_bound(&e, token_in.clone()), "ERR_NOT_BOUND");
-        assert!(
+    ) -> Result<i128, Error> {
+        require(read_finalize(&e), Error::NotFinalized)?;
+        require(check_record_bound(&e, token_in.clone()), Error::NotBound)?;
+        require(
             token_amount_in
                 <= c_mul(
                     read_record(&e)
@@ -574,11 +853,18 @@ impl TokenTrait for CometPoolContract {
                         .unwrap()
                         .unwrap()
                         .balance,
-                    MAX_IN_RATIO
-                )
-                .unwrap(),
-            "ERR_MAX_IN_RATIO"
-        );
+                    MAX_IN_RATIO,
+                )?,
+            Error::MaxInRatio,
+        )?;
+        let tokens_in = vec![&e, token_in.clone()];
+        let swap_fee = hooks::before_join(
+            &e,
+            &user,
+            &tokens_in,
+            min_pool_amount_out,
+            read_swap_fee(&e),
+        )?;
         let mut in_record = read_record(&e).get(token_in.clone()).unwrap().unwrap();
         let pool_amount_out = calc_lp_token_amount_given_token_deposits_in(
             in_record.balance,
@@ -586,27 +872,39 @@ impl TokenTrait for CometPoolContract {
             get_total_shares(&e),
             read_total_weight(&e),
             token_amount_in,
-            read_swap_fee(&e),
-        );
-        assert!(pool_amount_out >= min_pool_amount_out, "ERR_LIMIT_OUT");
-        in_record.balance = c_add(in_record.balance, token_amount_in).unwrap();
+            swap_fee,
+        )?;
+        require(pool_amount_out >= min_pool_amount_out, Error::LimitOut)?;
+        in_record.balance = c_add(in_record.balance, token_amount_in)?;
+        let fee = earnings::single_sided_fee(
+            token_amount_in,
+            in_record.denorm,
+            read_total_weight(&e),
+            swap_fee,
+        )?;
 
         let mut record_map = read_record(&e);
         record_map.set(token_in.clone(), in_record);
         write_record(&e, record_map);
 
-        let event: JoinEvent = JoinEvent {
-            caller: user.clone(),
-            token_in: token_in.clone(),
-            token_amount_in: token_amount_in,
-        };
-        e.events()
-            .publish((Symbol::short("LOG"), Symbol::short("JOIN")), event);
+        join_event(
+            &e,
+            JoinEvent {
+                caller: user.clone(),
+                token_in: token_in.clone(),
+                token_amount_in: token_amount_in,
+                pool_amount_out,
+            },
+        );
 
         pull_underlying(&e, &token_in, user.clone(), token_amount_in);
-        mint_shares(e, user.clone(), pool_amount_out);
+        earnings::accrue_fee(&e, token_in.clone(), fee)?;
+        mint_shares(e.clone(), user.clone(), pool_amount_out);
+        publish_snapshot(&e, &tokens_in)?;
+        let amounts_in = vec![&e, token_amount_in];
+        hooks::after_join(&e, &user, &tokens_in, &amounts_in, pool_amount_out);
 
-        pool_amount_out
+        Ok(pool_amount_out)
     }
 
     fn dep_lp_tokn_amt_out_get_tokn_in(
@@ -615,10 +913,13 @@ impl TokenTrait for CometPoolContract {
         pool_amount_out: i128,
         max_amount_in: i128,
         user: Address,
-    ) -> i128 {
-        assert!(read_finalize(&e), "ERR_NOT_FINALIZED");
-        assert!(check_record_bound(&e, token_in.clone()), "ERR_NOT_BOUND");
+    ) -> Result<i128, Error> {
+        require(read_finalize(&e), Error::NotFinalized)?;
+        require(check_record_bound(&e, token_in.clone()), Error::NotBound)?;
 
+        let tokens_in = vec![&e, token_in.clone()];
+        let swap_fee =
+            hooks::before_join(&e, &user, &tokens_in, pool_amount_out, read_swap_fee(&e))?;
         let mut in_record: Record = read_record(&e).get(token_in.clone()).unwrap().unwrap();
 
         let token_amount_in = calc_token_deposits_in_given_lp_token_amount(
@@ -627,11 +928,11 @@ impl TokenTrait for CometPoolContract {
             get_total_shares(&e),
             read_total_weight(&e),
             pool_amount_out,
-            read_swap_fee(&e),
-        );
-        assert!(token_amount_in != 0, "ERR_MATH_APPROX");
-        assert!(token_amount_in <= max_amount_in, "ERR_LIMIT_IN");
-        assert!(
+            swap_fee,
+        )?;
+        require(token_amount_in != 0, Error::MathApprox)?;
+        require(token_amount_in <= max_amount_in, Error::LimitIn)?;
+        require(
             token_amount_in
                 <= c_mul(
                     read_record(&e)
@@ -639,29 +940,40 @@ impl TokenTrait for CometPoolContract {
                         .unwrap()
                         .unwrap()
                         .balance,
-                    MAX_IN_RATIO
-                )
-                .unwrap(),
-            "ERR_MAX_IN_RATIO"
-        );
-        in_record.balance = c_add(in_record.balance, token_amount_in).unwrap();
+                    MAX_IN_RATIO,
+                )?,
+            Error::MaxInRatio,
+        )?;
+        in_record.balance = c_add(in_record.balance, token_amount_in)?;
+        let fee = earnings::single_sided_fee(
+            token_amount_in,
+            in_record.denorm,
+            read_total_weight(&e),
+            swap_fee,
+        )?;
 
         let mut record_map = read_record(&e);
         record_map.set(token_in.clone(), in_record);
         write_record(&e, record_map);
 
-        let event: JoinEvent = JoinEvent {
-            caller: user.clone(),
-            token_in: token_in.clone(),
-            token_amount_in: token_amount_in,
-        };
-        e.events()
-            .publish((Symbol::short("LOG"), Symbol::short("JOIN")), event);
+        join_event(
+            &e,
+            JoinEvent {
+                caller: user.clone(),
+                token_in: token_in.clone(),
+                token_amount_in: token_amount_in,
+                pool_amount_out,
+            },
+        );
 
         pull_underlying(&e, &token_in, user.clone(), token_amount_in);
-        mint_shares(e, user.clone(), pool_amount_out);
+        earnings::accrue_fee(&e, token_in.clone(), fee)?;
+        mint_shares(e.clone(), user.clone(), pool_amount_out);
+        publish_snapshot(&e, &tokens_in)?;
+        let amounts_in = vec![&e, token_amount_in];
+        hooks::after_join(&e, &user, &tokens_in, &amounts_in, pool_amount_out);
 
-        token_amount_in
+        Ok(token_amount_in)
     }
 
     fn wdr_tokn_amt_in_get_lp_tokns_out(
@@ -670,11 +982,14 @@ impl TokenTrait for CometPoolContract {
         pool_amount_in: i128,
         min_amount_out: i128,
         user: Address,
-    ) -> i128 {
+    ) -> Result<i128, Error> {
         user.require_auth();
-        assert!(read_finalize(&e), "ERR_NOT_FINALIZED");
-        assert!(check_record_bound(&e, token_out.clone()), "ERR_NOT_BOUND");
+        require(read_finalize(&e), Error::NotFinalized)?;
+        require(check_record_bound(&e, token_out.clone()), Error::NotBound)?;
 
+        let tokens_out = vec![&e, token_out.clone()];
+        let swap_fee =
+            hooks::before_exit(&e, &user, &tokens_out, pool_amount_in, read_swap_fee(&e))?;
         let mut out_record: Record = read_record(&e).get(token_out.clone()).unwrap().unwrap();
 
         let token_amount_out = calc_token_withdrawal_amount_given_lp_token_amount(
@@ -683,11 +998,11 @@ impl TokenTrait for CometPoolContract {
             get_total_shares(&e),
             read_total_weight(&e),
             pool_amount_in,
-            read_swap_fee(&e),
-        );
+            swap_fee,
+        )?;
 
-        assert!(token_amount_out >= min_amount_out, "ERR_LIMIT_OUT");
-        assert!(
+        require(token_amount_out >= min_amount_out, Error::LimitOut)?;
+        require(
             token_amount_out
                 <= c_mul(
                     read_record(&e)
@@ -695,33 +1010,45 @@ impl TokenTrait for CometPoolContract {
                         .unwrap()
                         .unwrap()
                         .balance,
-                    MAX_OUT_RATIO
-                )
-                .unwrap(),
-            "ERR_MAX_OUT_RATIO"
-        );
-        out_record.balance = c_sub(out_record.balance, token_amount_out).unwrap();
-        let exit_fee = c_mul(pool_amount_in, EXIT_FEE).unwrap();
+                    MAX_OUT_RATIO,
+                )?,
+            Error::MaxOutRatio,
+        )?;
+        out_record.balance = c_sub(out_record.balance, token_amount_out)?;
+        let fee = earnings::single_sided_fee(
+            token_amount_out,
+            out_record.denorm,
+            read_total_weight(&e),
+            swap_fee,
+        )?;
+        let exit_fee = c_mul(pool_amount_in, EXIT_FEE)?;
 
-        let event: ExitEvent = ExitEvent {
-            caller: user.clone(),
-            token_out: token_out.clone(),
-            token_amount_out: token_amount_out,
-        };
-        e.events()
-            .publish((Symbol::short("LOG"), Symbol::short("EXIT")), event);
+        exit_event(
+            &e,
+            ExitEvent {
+                caller: user.clone(),
+                token_out: token_out.clone(),
+                token_amount_out: token_amount_out,
+                pool_amount_in,
+            },
+        );
 
         pull_shares(&e, user.clone(), pool_amount_in);
-        burn_shares(&e, c_sub(pool_amount_in, EXIT_FEE).unwrap());
+        burn_shares(&e, c_sub(pool_amount_in, EXIT_FEE)?)?;
         let factory = read_factory(&e);
         push_shares(&e, factory, EXIT_FEE);
-        push_underlying(&e, &token_out, user, token_amount_out);
+        push_underlying(&e, &token_out, user.clone(), token_amount_out);
+        // Charged after the burn so the fee goes to the LPs who stay
+        earnings::accrue_fee(&e, token_out.clone(), fee)?;
 
         let mut record_map = read_record(&e);
         record_map.set(token_out, out_record);
         write_record(&e, record_map);
+        publish_snapshot(&e, &tokens_out)?;
+        let amounts_out = vec![&e, token_amount_out];
+        hooks::after_exit(&e, &user, &tokens_out, &amounts_out, pool_amount_in);
 
-        token_amount_out
+        Ok(token_amount_out)
     }
 
     fn wdr_tokn_amt_out_get_lp_tokns_in(
@@ -730,11 +1057,11 @@ impl TokenTrait for CometPoolContract {
         token_amount_out: i128,
         max_pool_amount_in: i128,
         user: Address,
-    ) -> i128 {
+    ) -> Result<i128, Error> {
         user.require_auth();
-        assert!(read_finalize(&e), "ERR_NOT_FINALIZED");
-        assert!(check_record_bound(&e, token_out.clone()), "ERR_NOT_BOUND");
-        assert!(
+        require(read_finalize(&e), Error::NotFinalized)?;
+        require(check_record_bound(&e, token_out.clone()), Error::NotBound)?;
+        require(
             token_amount_out
                 <= c_mul(
                     read_record(&e)
@@ -742,11 +1069,18 @@ impl TokenTrait for CometPoolContract {
                         .unwrap()
                         .unwrap()
                         .balance,
-                    MAX_OUT_RATIO
-                )
-                .unwrap(),
-            "ERR_MAX_OUT_RATIO"
-        );
+                    MAX_OUT_RATIO,
+                )?,
+            Error::MaxOutRatio,
+        )?;
+        let tokens_out = vec![&e, token_out.clone()];
+        let swap_fee = hooks::before_exit(
+            &e,
+            &user,
+            &tokens_out,
+            max_pool_amount_in,
+            read_swap_fee(&e),
+        )?;
         let mut out_record: Record = read_record(&e).get(token_out.clone()).unwrap().unwrap();
         let pool_amount_in = calc_lp_token_amount_given_token_withdrawal_amount(
             out_record.balance,
@@ -754,55 +1088,513 @@ impl TokenTrait for CometPoolContract {
             get_total_shares(&e),
             read_total_weight(&e),
             token_amount_out,
-            read_swap_fee(&e),
-        );
+            swap_fee,
+        )?;
 
-        assert!(pool_amount_in != 0, "ERR_MATH_APPROX");
-        assert!(pool_amount_in <= max_pool_amount_in, "ERR_LIMIT_IN");
-        out_record.balance = c_sub(out_record.balance, token_amount_out).unwrap();
-        let exit_fee = c_mul(pool_amount_in, EXIT_FEE).unwrap();
-        let event: ExitEvent = ExitEvent {
-            caller: user.clone(),
-            token_out: token_out.clone(),
-            token_amount_out: token_amount_out,
-        };
-        e.events()
-            .publish((Symbol::short("LOG"), Symbol::short("EXIT")), event);
+        require(pool_amount_in != 0, Error::MathApprox)?;
+        require(pool_amount_in <= max_pool_amount_in, Error::LimitIn)?;
+        out_record.balance = c_sub(out_record.balance, token_amount_out)?;
+        let fee = earnings::single_sided_fee(
+            token_amount_out,
+            out_record.denorm,
+            read_total_weight(&e),
+            swap_fee,
+        )?;
+        let exit_fee = c_mul(pool_amount_in, EXIT_FEE)?;
+        exit_event(
+            &e,
+            ExitEvent {
+                caller: user.clone(),
+                token_out: token_out.clone(),
+                token_amount_out: token_amount_out,
+                pool_amount_in,
+            },
+        );
 
         pull_shares(&e, user.clone(), pool_amount_in);
-        burn_shares(&e, c_sub(pool_amount_in, EXIT_FEE).unwrap());
+        burn_shares(&e, c_sub(pool_amount_in, EXIT_FEE)?)?;
         let factory = read_factory(&e);
         push_shares(&e, factory, EXIT_FEE);
-        push_underlying(&e, &token_out, user, token_amount_out);
+        push_underlying(&e, &token_out, user.clone(), token_amount_out);
+        // Charged after the burn so the fee goes to the LPs who stay
+        earnings::accrue_fee(&e, token_out.clone(), fee)?;
 
-        pool_amount_in
-    }
+        let mut record_map = read_record(&e);
+        record_map.set(token_out, out_record);
+        write_record(&e, record_map);
+        publish_snapshot(&e, &tokens_out)?;
+        let amounts_out = vec![&e, token_amount_out];
+        hooks::after_exit(&e, &user, &tokens_out, &amounts_out, pool_amount_in);
 
-    fn set_swap_fee(e: Env, fee: i128, caller: Address) {
-        assert!(!read_finalize(&e), "ERR_FINALIZED");
-        assert!(fee >= MIN_FEE, "ERR_MIN_FEE");
-        assert!(fee <= MAX_FEE, "ERR_MAX_FEED");
-        assert!(caller == read_controller(&e), "ERR_NOT_CONTROLLER");
-        caller.require_auth();
-        write_swap_fee(&e, fee);
+        Ok(pool_amount_in)
     }
 
-    fn set_controller(e: Env, caller: Address, manager: Address) {
-        assert!(caller == read_controller(&e), "ERR_NOT_CONTROLLER");
-        caller.require_auth();
-        write_controller(&e, manager);
-    }
+    fn join_exact_tokens_in(
+        e: Env,
+        amounts_in: Vec<i128>,
+        min_pool_amount_out: i128,
+        user: Address,
+    ) -> Result<i128, Error> {
+        require(read_finalize(&e), Error::NotFinalized)?;
+        user.require_auth();
 
-    fn set_public_swap(e: Env, caller: Address, val: bool) {
-        assert!(caller == read_controller(&e), "ERR_NOT_CONTROLLER");
-        assert!(read_finalize(&e), "ERR_NOT_FINALIZED");
-        caller.require_auth();
-        write_public_swap(&e, val);
-    }
+        let tokens = read_tokens(&e);
+        let swap_fee =
+            hooks::before_join(&e, &user, &tokens, min_pool_amount_out, read_swap_fee(&e))?;
+        let pool_amount_out = calc_join_exact_tokens_in(&e, &amounts_in, swap_fee)?;
+        require(pool_amount_out != 0, Error::MathApprox)?;
+        require(pool_amount_out >= min_pool_amount_out, Error::LimitOut)?;
 
-    fn get_total_denormalized_weight(e: Env) -> i128 {
-        read_total_weight(&e)
-    }
+        let mut records = read_record(&e);
+        let total_weight = read_total_weight(&e);
+        for i in 0..tokens.len() {
+            let token_amount_in = amounts_in.get(i).unwrap().unwrap();
+            if token_amount_in == 0 {
+                continue;
+            }
+            let t = tokens.get(i).unwrap().unwrap();
+            let mut rec = records.get(t.clone()).unwrap().unwrap();
+            let fee =
+                earnings::single_sided_fee(token_amount_in, rec.denorm, total_weight, swap_fee)?;
+            rec.balance = c_add(rec.balance, token_amount_in)?;
+            records.set(t.clone(), rec);
+            join_event(
+                &e,
+                JoinEvent {
+                    caller: user.clone(),
+                    token_in: t.clone(),
+                    token_amount_in: token_amount_in,
+                    pool_amount_out,
+                },
+            );
+            pull_underlying(&e, &t, user.clone(), token_amount_in);
+            earnings::accrue_fee(&e, t.clone(), fee)?;
+        }
+
+        // The caller's fee checkpoint moves with its balance in mint_shares
+        write_record(&e, records);
+        mint_shares(e.clone(), user.clone(), pool_amount_out);
+        publish_snapshot(&e, &tokens)?;
+        hooks::after_join(&e, &user, &tokens, &amounts_in, pool_amount_out);
+
+        Ok(pool_amount_out)
+    }
+
+    fn quote_join_exact_tokens_in(e: Env, amounts_in: Vec<i128>) -> Result<i128, Error> {
+        require(read_finalize(&e), Error::NotFinalized)?;
+        calc_join_exact_tokens_in(&e, &amounts_in, read_swap_fee(&e))
+    }
+
+    fn zap_in(
+        e: Env,
+        token_in: Address,
+        token_amount_in: i128,
+        min_pool_amount_out: i128,
+        user: Address,
+    ) -> Result<i128, Error> {
+        require(read_finalize(&e), Error::NotFinalized)?;
+        require(read_public_swap(&e), Error::SwapNotPublic)?;
+        require(check_record_bound(&e, token_in.clone()), Error::NotBound)?;
+        require(token_amount_in >= 0, Error::NegativeAmount)?;
+        user.require_auth();
+
+        let tokens = read_tokens(&e);
+        let swap_fee = zap_swap_fee(&e, &user, &tokens, min_pool_amount_out)?;
+        let zap = calc_zap_in(&e, &token_in, token_amount_in, swap_fee)?;
+        require(zap.pool_amount_out != 0, Error::MathApprox)?;
+        require(zap.pool_amount_out >= min_pool_amount_out, Error::LimitOut)?;
+
+        pull_underlying(&e, &token_in, user.clone(), token_amount_in);
+        for i in 0..tokens.len() {
+            let t = tokens.get(i).unwrap().unwrap();
+            let swap_amount_in = zap.swap_amounts_in.get(i).unwrap().unwrap();
+            let swap_amount_out = zap.swap_amounts_out.get(i).unwrap().unwrap();
+            if swap_amount_in != 0 {
+                swap_event(
+                    &e,
+                    SwapEvent {
+                        caller: user.clone(),
+                        token_in: token_in.clone(),
+                        token_out: t.clone(),
+                        token_amount_in: swap_amount_in,
+                        token_amount_out: swap_amount_out,
+                        referrer: None,
+                        referral_amount: 0,
+                    },
+                );
+                earnings::accrue_fee(&e, token_in.clone(), c_mul(swap_amount_in, swap_fee)?)?;
+            }
+
+            let token_amount_joined = zap.amounts_in.get(i).unwrap().unwrap();
+            join_event(
+                &e,
+                JoinEvent {
+                    caller: user.clone(),
+                    token_in: t.clone(),
+                    token_amount_in: token_amount_joined,
+                    pool_amount_out: zap.pool_amount_out,
+                },
+            );
+
+            // Whatever the join couldn't use goes back to the user
+            let leftover = c_sub(zap.holdings.get(i).unwrap().unwrap(), token_amount_joined)?;
+            if leftover > 0 {
+                push_underlying(&e, &t, user.clone(), leftover);
+            }
+        }
+
+        write_record(&e, zap.records);
+        mint_shares(e.clone(), user.clone(), zap.pool_amount_out);
+        publish_snapshot(&e, &tokens)?;
+        hooks::after_join(&e, &user, &tokens, &zap.amounts_in, zap.pool_amount_out);
+
+        Ok(zap.pool_amount_out)
+    }
+
+    // Priced with the fee `user` would pay, quoted as a zap without a minimum
+    fn quote_zap_in(
+        e: Env,
+        token_in: Address,
+        token_amount_in: i128,
+        user: Address,
+    ) -> Result<i128, Error> {
+        require(read_finalize(&e), Error::NotFinalized)?;
+        require(check_record_bound(&e, token_in.clone()), Error::NotBound)?;
+        let swap_fee = zap_swap_fee(&e, &user, &read_tokens(&e), 0)?;
+        Ok(calc_zap_in(&e, &token_in, token_amount_in, swap_fee)?.pool_amount_out)
+    }
+
+    fn exit_exact_tokens_out(
+        e: Env,
+        amounts_out: Vec<i128>,
+        max_pool_amount_in: i128,
+        user: Address,
+    ) -> Result<i128, Error> {
+        user.require_auth();
+        require(read_finalize(&e), Error::NotFinalized)?;
+
+        let tokens = read_tokens(&e);
+        let swap_fee =
+            hooks::before_exit(&e, &user, &tokens, max_pool_amount_in, read_swap_fee(&e))?;
+        let pool_amount_in = calc_exit_exact_tokens_out(&e, &amounts_out, swap_fee)?;
+        require(pool_amount_in != 0, Error::MathApprox)?;
+        require(pool_amount_in <= max_pool_amount_in, Error::LimitIn)?;
+
+        pull_shares(&e, user.clone(), pool_amount_in);
+        burn_shares(&e, c_sub(pool_amount_in, EXIT_FEE)?)?;
+        let factory = read_factory(&e);
+        push_shares(&e, factory, EXIT_FEE);
+
+        let mut records = read_record(&e);
+        let total_weight = read_total_weight(&e);
+        for i in 0..tokens.len() {
+            let token_amount_out = amounts_out.get(i).unwrap().unwrap();
+            if token_amount_out == 0 {
+                continue;
+            }
+            let t = tokens.get(i).unwrap().unwrap();
+            let mut rec = records.get(t.clone()).unwrap().unwrap();
+            let fee =
+                earnings::single_sided_fee(token_amount_out, rec.denorm, total_weight, swap_fee)?;
+            rec.balance = c_sub(rec.balance, token_amount_out)?;
+            records.set(t.clone(), rec);
+            exit_event(
+                &e,
+                ExitEvent {
+                    caller: user.clone(),
+                    token_out: t.clone(),
+                    token_amount_out: token_amount_out,
+                    pool_amount_in,
+                },
+            );
+            push_underlying(&e, &t, user.clone(), token_amount_out);
+            // Charged after the burn so the fee goes to the LPs who stay
+            earnings::accrue_fee(&e, t.clone(), fee)?;
+        }
+
+        write_record(&e, records);
+        publish_snapshot(&e, &tokens)?;
+        hooks::after_exit(&e, &user, &tokens, &amounts_out, pool_amount_in);
+
+        Ok(pool_amount_in)
+    }
+
+    fn quote_exit_exact_tokens_out(e: Env, amounts_out: Vec<i128>) -> Result<i128, Error> {
+        require(read_finalize(&e), Error::NotFinalized)?;
+        calc_exit_exact_tokens_out(&e, &amounts_out, read_swap_fee(&e))
+    }
+
+    fn set_swap_fee(e: Env, fee: i128, caller: Address) -> Result<(), Error> {
+        require(!read_finalize(&e), Error::Finalized)?;
+        require(fee >= MIN_FEE, Error::MinFee)?;
+        require(fee <= MAX_FEE, Error::MaxFee)?;
+        require_role(&e, &caller, Role::FeeManager)?;
+        caller.require_auth();
+        let old_fee = read_swap_fee(&e);
+        write_swap_fee(&e, fee);
+        swap_fee_event(
+            &e,
+            SwapFeeEvent {
+                caller,
+                old_fee,
+                new_fee: fee,
+            },
+        );
+
+        Ok(())
+    }
+
+    // Moves the swap fee linearly from its current value to `target_fee` by `end_time`, finalized or not
+    fn ramp_swap_fee(
+        e: Env,
+        caller: Address,
+        target_fee: i128,
+        end_time: u64,
+    ) -> Result<(), Error> {
+        require(target_fee >= MIN_FEE, Error::MinFee)?;
+        require(target_fee <= MAX_FEE, Error::MaxFee)?;
+        require_role(&e, &caller, Role::FeeManager)?;
+        caller.require_auth();
+        let now = e.ledger().timestamp();
+        require(end_time > now, Error::RampEndTime)?;
+
+        let ramp = FeeRamp {
+            start_fee: read_swap_fee(&e),
+            target_fee,
+            start_time: now,
+            end_time,
+        };
+        write_swap_fee_ramp(&e, ramp.clone());
+        fee_ramp_event(
+            &e,
+            FeeRampEvent {
+                caller,
+                start_fee: ramp.start_fee,
+                target_fee: ramp.target_fee,
+                start_time: ramp.start_time,
+                end_time: ramp.end_time,
+            },
+        );
+
+        Ok(())
+    }
+
+    fn get_swap_fee_ramp(e: Env) -> Option<FeeRamp> {
+        read_swap_fee_ramp(&e)
+    }
+
+    // LPs holding enough shares can put a new swap fee to a vote on finalized pools
+    fn propose_swap_fee(e: Env, proposer: Address, fee: i128) -> Result<u32, Error> {
+        proposer.require_auth();
+        governance::propose(&e, proposer, fee)
+    }
+
+    fn vote_swap_fee(e: Env, voter: Address, proposal_id: u32, support: bool) -> Result<(), Error> {
+        voter.require_auth();
+        governance::vote(&e, voter, proposal_id, support)
+    }
+
+    // Anyone can apply a proposal once it passed and its delay is over
+    fn execute_swap_fee(e: Env, proposal_id: u32) -> Result<(), Error> {
+        governance::execute(&e, proposal_id)
+    }
+
+    fn get_fee_proposal(e: Env, proposal_id: u32) -> Result<FeeProposal, Error> {
+        governance::read_proposal(&e, proposal_id)
+    }
+
+    fn set_min_liquidity(e: Env, caller: Address, amount: i128) -> Result<(), Error> {
+        require(!read_finalize(&e), Error::Finalized)?;
+        require(amount >= 0, Error::NegativeMinLiquidity)?;
+        require(amount <= MAX_MIN_LIQUIDITY, Error::MaxMinLiquidity)?;
+        require_role(&e, &caller, Role::Admin)?;
+        caller.require_auth();
+        let old_amount = read_min_liquidity(&e);
+        write_min_liquidity(&e, amount);
+        min_liquidity_event(
+            &e,
+            MinLiquidityEvent {
+                caller,
+                old_amount,
+                new_amount: amount,
+            },
+        );
+
+        Ok(())
+    }
+
+    fn get_min_liquidity(e: Env) -> i128 {
+        read_min_liquidity(&e)
+    }
+
+    fn set_fee_multiplier(
+        e: Env,
+        caller: Address,
+        user: Address,
+        multiplier: i128,
+    ) -> Result<(), Error> {
+        require_role(&e, &caller, Role::FeeManager)?;
+        require(multiplier >= 0, Error::MinFeeMultiplier)?;
+        require(multiplier <= BONE, Error::MaxFeeMultiplier)?;
+        caller.require_auth();
+        let old_multiplier = read_fee_multiplier(&e, user.clone());
+        write_fee_multiplier(&e, user.clone(), multiplier);
+        fee_multiplier_event(
+            &e,
+            FeeMultiplierEvent {
+                caller,
+                user,
+                old_multiplier,
+                new_multiplier: multiplier,
+            },
+        );
+
+        Ok(())
+    }
+
+    fn get_effective_fee(e: Env, user: Address) -> Result<i128, Error> {
+        effective_swap_fee(&e, user, read_swap_fee(&e))
+    }
+
+    fn set_max_referral_fee(e: Env, caller: Address, fee: i128) -> Result<(), Error> {
+        require_role(&e, &caller, Role::FeeManager)?;
+        require(fee >= 0, Error::MinReferralFee)?;
+        require(fee <= MAX_REFERRAL_FEE, Error::MaxReferralFee)?;
+        caller.require_auth();
+        let old_fee = read_max_referral_fee(&e);
+        write_max_referral_fee(&e, fee);
+        max_referral_fee_event(
+            &e,
+            MaxReferralFeeEvent {
+                caller,
+                old_fee,
+                new_fee: fee,
+            },
+        );
+
+        Ok(())
+    }
+
+    fn get_max_referral_fee(e: Env) -> i128 {
+        read_max_referral_fee(&e)
+    }
+
+    fn set_controller(e: Env, caller: Address, manager: Address) -> Result<(), Error> {
+        require_role(&e, &caller, Role::Admin)?;
+        caller.require_auth();
+        let old_controller = read_controller(&e);
+        write_controller(&e, manager.clone());
+        controller_event(
+            &e,
+            ControllerEvent {
+                caller,
+                old_controller,
+                new_controller: manager,
+            },
+        );
+
+        Ok(())
+    }
+
+    fn grant_role(e: Env, caller: Address, role: Role, account: Address) -> Result<(), Error> {
+        require_role(&e, &caller, Role::Admin)?;
+        caller.require_auth();
+        write_role(&e, role, account.clone(), true);
+        role_granted_event(&e, role, account, caller);
+
+        Ok(())
+    }
+
+    fn revoke_role(e: Env, caller: Address, role: Role, account: Address) -> Result<(), Error> {
+        require_role(&e, &caller, Role::Admin)?;
+        caller.require_auth();
+        write_role(&e, role, account.clone(), false);
+        role_revoked_event(&e, role, account, caller);
+
+        Ok(())
+    }
+
+    // The controller can't renounce, it stays admin until it's replaced with set_controller
+    fn renounce_role(e: Env, account: Address, role: Role) {
+        account.require_auth();
+        write_role(&e, role, account.clone(), false);
+        role_revoked_event(&e, role, account.clone(), account);
+    }
+
+    fn has_role(e: Env, role: Role, account: Address) -> bool {
+        roles::has_role(&e, role, account)
+    }
+
+    // Sends out tokens held by the pool on top of what its records account for
+    fn rescue_token(
+        e: Env,
+        caller: Address,
+        token: Address,
+        to: Address,
+        amount: i128,
+    ) -> Result<(), Error> {
+        require_role(&e, &caller, Role::Rescuer)?;
+        caller.require_auth();
+        require(amount >= 0, Error::NegativeAmount)?;
+        // The pool's own shares include the locked minimum liquidity
+        require(token != e.current_contract_address(), Error::RescueShares)?;
+
+        let accounted = match read_record(&e).get(token.clone()) {
+            Some(record) => record.unwrap().balance,
+            None => 0,
+        };
+        let held = token::Client::new(&e, &token.contract_id().unwrap())
+            .balance(&e.current_contract_address());
+        require(amount <= c_sub(held, accounted)?, Error::RescueAmount)?;
+
+        push_underlying(&e, &token, to.clone(), amount);
+        rescue_event(&e, caller, token, to, amount);
+
+        Ok(())
+    }
+
+    fn set_public_swap(e: Env, caller: Address, val: bool) -> Result<(), Error> {
+        require_role(&e, &caller, Role::Pauser)?;
+        require(read_finalize(&e), Error::NotFinalized)?;
+        caller.require_auth();
+        let old_value = read_public_swap(&e);
+        write_public_swap(&e, val);
+        public_swap_event(
+            &e,
+            PublicSwapEvent {
+                caller,
+                old_value,
+                new_value: val,
+            },
+        );
+
+        Ok(())
+    }
+
+    fn set_legacy_events(e: Env, caller: Address, val: bool) -> Result<(), Error> {
+        require_role(&e, &caller, Role::Admin)?;
+        caller.require_auth();
+        let old_value = read_legacy_events(&e);
+        write_legacy_events(&e, val);
+        legacy_events_event(
+            &e,
+            LegacyEventsEvent {
+                caller,
+                old_value,
+                new_value: val,
+            },
+        );
+
+        Ok(())
+    }
+
+    fn get_legacy_events(e: Env) -> bool {
+        read_legacy_events(&e)
+    }
+
+    fn get_total_denormalized_weight(e: Env) -> i128 {
+        read_total_weight(&e)
+    }
 
     fn get_num_tokens(e: Env) -> u32 {
         let token_vec = read_tokens(&e);
@@ -813,32 +1605,39 @@ impl TokenTrait for CometPoolContract {
         read_tokens(&e)
     }
 
-    fn get_final_tokens(e: Env) -> Vec<Address> {
-        assert!(read_finalize(&e), "ERR_NOT_FINALIZED");
-        read_tokens(&e)
+    fn get_final_tokens(e: Env) -> Result<Vec<Address>, Error> {
+        require(read_finalize(&e), Error::NotFinalized)?;
+        Ok(read_tokens(&e))
     }
 
-    fn get_balance(e: Env, token: Address) -> i128 {
-        let val = read_record(&e).get(token).unwrap().unwrap();
-        assert!(val.bound, "ERR_NOT_BOUND");
-        val.balance
+    fn get_balance(e: Env, token: Address) -> Result<i128, Error> {
+        let val = read_record(&e).get(token).ok_or(Error::NotBound)?.unwrap();
+        require(val.bound, Error::NotBound)?;
+        Ok(val.balance)
     }
 
-    fn get_denormalized_weight(e: Env, token: Address) -> i128 {
-        assert!(check_record_bound(&e, token.clone()), "ERR_NOT_BOUND");
+    fn get_denormalized_weight(e: Env, token: Address) -> Result<i128, Error> {
+        require(check_record_bound(&e, token.clone()), Error::NotBound)?;
         let val = read_record(&e).get(token).unwrap().unwrap();
-        val.denorm
+        Ok(val.denorm)
     }
 
-    fn get_normalized_weight(e: Env, token: Address) -> i128 {
-        assert!(check_record_bound(&e, token.clone()), "ERR_NOT_BOUND");
+    fn get_normalized_weight(e: Env, token: Address) -> Result<i128, Error> {
+        require(check_record_bound(&e, token.clone()), Error::NotBound)?;
         let val = read_record(&e).get(token).unwrap().unwrap();
-        c_div(val.denorm, read_total_weight(&e)).unwrap()
+        c_div(val.denorm, read_total_weight(&e))
     }
 
-    fn get_spot_price(e: Env, token_in: Address, token_out: Address) -> i128 {
-        let in_record = read_record(&e).get(token_in).unwrap().unwrap();
-        let out_record: Record = read_record(&e).get(token_out).unwrap().unwrap();
+    fn get_spot_price(e: Env, token_in: Address, token_out: Address) -> Result<i128, Error> {
+        let in_record = read_record(&e)
+            .get(token_in)
+            .ok_or(Error::NotBound)?
+            .unwrap();
+        let out_record = read_record(&e)
+            .get(token_out)
+            .ok_or(Error::NotBound)?
+            .unwrap();
+        require(in_record.bound && out_record.bound, Error::NotBound)?;
         calc_spot_price(
             in_record.balance,
             in_record.denorm,
@@ -846,16 +1645,26 @@ impl TokenTrait for CometPoolContract {
             out_record.denorm,
             read_swap_fee(&e),
         )
-        .unwrap()
     }
 
     fn get_swap_fee(e: Env) -> i128 {
         read_swap_fee(&e)
     }
 
-    fn get_spot_price_sans_fee(e: Env, token_in: Address, token_out: Address) -> i128 {
-        let in_record = read_record(&e).get(token_in).unwrap().unwrap();
-        let out_record = read_record(&e).get(token_out).unwrap().unwrap();
+    fn get_spot_price_sans_fee(
+        e: Env,
+        token_in: Address,
+        token_out: Address,
+    ) -> Result<i128, Error> {
+        let in_record = read_record(&e)
+            .get(token_in)
+            .ok_or(Error::NotBound)?
+            .unwrap();
+        let out_record = read_record(&e)
+            .get(token_out)
+            .ok_or(Error::NotBound)?
+            .unwrap();
+        require(in_record.bound && out_record.bound, Error::NotBound)?;
         calc_spot_price(
             in_record.balance,
             in_record.denorm,
@@ -863,13 +1672,56 @@ impl TokenTrait for CometPoolContract {
             out_record.denorm,
             0,
         )
-        .unwrap()
     }
 
     fn share_id(e: Env) -> BytesN<32> {
         get_token_share(&e)
     }
 
+    // Underlying tokens an address's LP balance would redeem for, and their value in `numeraire`
+    fn get_position(e: Env, address: Address, numeraire: Address) -> Result<Position, Error> {
+        require(read_finalize(&e), Error::NotFinalized)?;
+        require(check_record_bound(&e, numeraire.clone()), Error::NotBound)?;
+
+        let balance = read_balance(&e, address);
+        let share = c_div(balance, get_total_shares(&e))?;
+        let records = read_record(&e);
+        let numeraire_record = records.get(numeraire).unwrap().unwrap();
+        let tokens = read_tokens(&e);
+        let mut amounts: Vec<i128> = vec![&e];
+        let mut value = 0;
+        for i in 0..tokens.len() {
+            let rec = records
+                .get(tokens.get(i).unwrap().unwrap())
+                .unwrap()
+                .unwrap();
+            let amount = c_mul(share, rec.balance)?;
+            // Units of the numeraire per unit of this token
+            let price = calc_spot_price(
+                numeraire_record.balance,
+                numeraire_record.denorm,
+                rec.balance,
+                rec.denorm,
+                0,
+            )?;
+            amounts.push_back(amount);
+            value = c_add(value, c_mul(amount, price)?)?;
+        }
+
+        Ok(Position {
+            balance,
+            share,
+            tokens,
+            amounts,
+            value,
+        })
+    }
+
+    // Swap fees credited to an LP's balance, per bound token
+    fn get_fees_earned(e: Env, lp: Address) -> Vec<i128> {
+        earnings::fees_earned(&e, lp.clone(), read_balance(&e, lp))
+    }
+
     fn is_public_swap(e: Env) -> bool {
         read_public_swap(&e)
     }
@@ -879,7 +1731,10 @@ impl TokenTrait for CometPoolContract {
     }
 
     fn is_bound(e: Env, t: Address) -> bool {
-        read_record(&e).get(t).unwrap().unwrap().bound
+        read_record(&e)
+            .get(t)
+            .map(|record| record.unwrap().bound)
+            .unwrap_or(false)
     }
 
     fn initialize(e: Env, admin: Address, decimal: u32, name: Bytes, symbol: Bytes) {
@@ -894,41 +1749,148 @@ impl TokenTrait for CometPoolContract {
     }
 
     fn allowance(e: Env, from: Address, spender: Address) -> i128 {
-        read_allowance(&e, from, spender)
+        read_allowance(&e, from, spender).amount
+    }
+
+    // Replaces the allowance, which can't be spent after `expiration_ledger`
+    fn approve(
+        e: Env,
+        from: Address,
+        spender: Address,
+        amount: i128,
+        expiration_ledger: u32,
+    ) -> Result<(), Error> {
+        from.require_auth();
+
+        check_nonnegative_amount(amount)?;
+        require(
+            amount == 0 || expiration_ledger >= e.ledger().sequence(),
+            Error::ExpirationInPast,
+        )?;
+
+        write_allowance(&e, from.clone(), spender.clone(), amount, expiration_ledger);
+        approve_event(&e, from, spender, amount, expiration_ledger);
+        Ok(())
     }
 
-    fn incr_allow(e: Env, from: Address, spender: Address, amount: i128) {
+    fn incr_allow(e: Env, from: Address, spender: Address, amount: i128) -> Result<(), Error> {
         from.require_auth();
 
-        check_nonnegative_amount(amount);
+        check_nonnegative_amount(amount)?;
 
         let allowance = read_allowance(&e, from.clone(), spender.clone());
         let new_allowance = allowance
+            .amount
             .checked_add(amount)
             .expect("Updated allowance doesn't fit in an i128");
 
-        write_allowance(&e, from.clone(), spender.clone(), new_allowance);
-        incr_allow_event(&e, from, spender, amount);
+        write_allowance(
+            &e,
+            from.clone(),
+            spender.clone(),
+            new_allowance,
+            allowance.expiration_ledger,
+        );
+        incr_allow_event(&e, from, spender, amount, allowance.expiration_ledger);
+        Ok(())
     }
 
-    fn decr_allow(e: Env, from: Address, spender: Address, amount: i128) {
+    fn decr_allow(e: Env, from: Address, spender: Address, amount: i128) -> Result<(), Error> {
         from.require_auth();
 
-        check_nonnegative_amount(amount);
+        check_nonnegative_amount(amount)?;
 
         let allowance = read_allowance(&e, from.clone(), spender.clone());
-        if amount >= allowance {
-            write_allowance(&e, from.clone(), spender.clone(), 0);
+        if amount >= allowance.amount {
+            write_allowance(
+                &e,
+                from.clone(),
+                spender.clone(),
+                0,
+                allowance.expiration_ledger,
+            );
         } else {
-            write_allowance(&e, from.clone(), spender.clone(), allowance - amount);
+            write_allowance(
+                &e,
+                from.clone(),
+                spender.clone(),
+                allowance.amount - amount,
+                allowance.expiration_ledger,
+            );
         }
-        decr_allow_event(&e, from, spender, amount);
+        decr_allow_event(&e, from, spender, amount, allowance.expiration_ledger);
+        Ok(())
+    }
+
+    // Registers the ed25519 key whose signatures `permit` accepts for `owner`
+    fn set_permit_key(e: Env, owner: Address, public_key: BytesN<32>) {
+        owner.require_auth();
+        write_permit_key(&e, owner, public_key);
+    }
+
+    // Sets the allowance from a payload signed by the owner's permit key, anyone can relay it
+    fn permit(
+        e: Env,
+        owner: Address,
+        spender: Address,
+        amount: i128,
+        expiry: u32,
+        signature: BytesN<64>,
+    ) -> Result<(), Error> {
+        check_nonnegative_amount(amount)?;
+
+        let payload = PermitPayload {
+            token: e.current_contract_address(),
+            owner: owner.clone(),
+            spender: spender.clone(),
+            amount,
+            nonce: read_nonce(&e, owner.clone()),
+            expiry,
+        };
+        use_permit(&e, payload, signature)?;
+
+        write_allowance(&e, owner.clone(), spender.clone(), amount, NO_EXPIRATION);
+        permit_event(&e, owner, spender, amount);
+        Ok(())
+    }
+
+    fn nonce(e: Env, owner: Address) -> i128 {
+        read_nonce(&e, owner)
     }
 
     fn balance(e: Env, id: Address) -> i128 {
         read_balance(&e, id)
     }
 
+    // LP balance at the end of a past ledger
+    fn balance_at(e: Env, id: Address, ledger: u32) -> i128 {
+        checkpoints::balance_at(&e, id, ledger)
+    }
+
+    // Total shares at the end of a past ledger
+    fn total_supply_at(e: Env, ledger: u32) -> i128 {
+        checkpoints::total_supply_at(&e, ledger)
+    }
+
+    // Gives `to` the voting power of `from`'s LP balance, including future changes to it
+    fn delegate(e: Env, from: Address, to: Address) {
+        from.require_auth();
+        votes::delegate(&e, from, to);
+    }
+
+    fn delegates(e: Env, id: Address) -> Option<Address> {
+        votes::read_delegate(&e, id)
+    }
+
+    fn get_votes(e: Env, id: Address) -> i128 {
+        votes::read_votes(&e, id)
+    }
+
+    // Voting power at the end of a past ledger
+    fn get_past_votes(e: Env, id: Address, ledger: u32) -> i128 {
+        votes::votes_at(&e, id, ledger)
+    }
+
     fn spendable(e: Env, id: Address) -> i128 {
         read_balance(&e, id)
     }
@@ -937,48 +1899,59 @@ impl TokenTrait for CometPoolContract {
         is_authorized(&e, id)
     }
 
-    fn xfer(e: Env, from: Address, to: Address, amount: i128) {
+    fn xfer(e: Env, from: Address, to: Address, amount: i128) -> Result<(), Error> {
         from.require_auth();
 
-        check_nonnegative_amount(amount);
+        check_nonnegative_amount(amount)?;
         spend_balance(&e, from.clone(), amount);
         receive_balance(&e, to.clone(), amount);
         transfer_event(&e, from, to, amount);
+        Ok(())
     }
 
-    fn xfer_from(e: Env, spender: Address, from: Address, to: Address, amount: i128) {
+    fn xfer_from(
+        e: Env,
+        spender: Address,
+        from: Address,
+        to: Address,
+        amount: i128,
+    ) -> Result<(), Error> {
         spender.require_auth();
 
-        check_nonnegative_amount(amount);
+        check_nonnegative_amount(amount)?;
         spend_allowance(&e, from.clone(), spender, amount);
         spend_balance(&e, from.clone(), amount);
         receive_balance(&e, to.clone(), amount);
-        transfer_event(&e, from, to, amount)
+        transfer_event(&e, from, to, amount);
+        Ok(())
     }
 
-    fn burn(e: Env, from: Address, amount: i128) {
+    fn burn(e: Env, from: Address, amount: i128) -> Result<(), Error> {
         from.require_auth();
 
-        check_nonnegative_amount(amount);
+        check_nonnegative_amount(amount)?;
         spend_balance(&e, from.clone(), amount);
         burn_event(&e, from, amount);
+        Ok(())
     }
 
-    fn burn_from(e: Env, spender: Address, from: Address, amount: i128) {
+    fn burn_from(e: Env, spender: Address, from: Address, amount: i128) -> Result<(), Error> {
         spender.require_auth();
 
-        check_nonnegative_amount(amount);
+        check_nonnegative_amount(amount)?;
         spend_allowance(&e, from.clone(), spender, amount);
         spend_balance(&e, from.clone(), amount);
-        burn_event(&e, from, amount)
+        burn_event(&e, from, amount);
+        Ok(())
     }
 
-    fn clawback(e: Env, admin: Address, from: Address, amount: i128) {
-        check_nonnegative_amount(amount);
+    fn clawback(e: Env, admin: Address, from: Address, amount: i128) -> Result<(), Error> {
+        check_nonnegative_amount(amount)?;
         check_admin(&e, &admin);
         admin.require_auth();
         spend_balance(&e, from.clone(), amount);
         clawback_event(&e, admin, from, amount);
+        Ok(())
     }
 
     fn set_auth(e: Env, admin: Address, id: Address, authorize: bool) {
@@ -988,12 +1961,13 @@ impl TokenTrait for CometPoolContract {
         set_auth_event(&e, admin, id, authorize);
     }
 
-    fn mint(e: Env, admin: Address, to: Address, amount: i128) {
-        check_nonnegative_amount(amount);
+    fn mint(e: Env, admin: Address, to: Address, amount: i128) -> Result<(), Error> {
+        check_nonnegative_amount(amount)?;
         check_admin(&e, &admin);
         admin.require_auth();
         receive_balance(&e, to.clone(), amount);
         mint_event(&e, admin, to, amount);
+        Ok(())
     }
 
     fn set_admin(e: Env, admin: Address, new_admin: Address) {
@@ -1033,6 +2007,148 @@ fn push_underlying(e: &Env, token: &Address, to: Address, amount: i128) {
     );
 }
 
+// Sends the referral cut of a swap input straight from the user to the referrer
+fn pay_referral(e: &Env, token: &Address, from: Address, referrer: Option<Address>, amount: i128) {
+    if let Some(referrer) = referrer {
+        if amount > 0 {
+            token::Client::new(e, &token.contract_id().unwrap()).xfer_from(
+                &e.current_contract_address(),
+                &from,
+                &referrer,
+                &amount,
+            );
+        }
+    }
+}
+
+// Sets a bound token's weight and balance, moving the difference from or to `admin`
+fn rebind_token(
+    e: &Env,
+    token: Address,
+    balance: i128,
+    denorm: i128,
+    admin: Address,
+) -> Result<(), Error> {
+    require(read_tokens(e).len() < MAX_BOUND_TOKENS, Error::MaxTokens)?;
+    require(check_record_bound(e, token.clone()), Error::NotBound)?;
+    require(denorm >= MIN_WEIGHT, Error::MinWeight)?;
+    require(denorm <= MAX_WEIGHT, Error::MaxWeight)?;
+    require(balance >= MIN_BALANCE, Error::MinBalance)?;
+
+    let mut record_map: Map<Address, Record> = read_record(e);
+    let mut record = record_map.get(token.clone()).unwrap().unwrap();
+    let old_weight = record.denorm;
+    let mut total_weight = read_total_weight(e);
+
+    if denorm > old_weight {
+        total_weight = c_add(total_weight, c_sub(denorm, old_weight)?)?;
+        write_total_weight(e, total_weight);
+        if total_weight > MAX_TOTAL_WEIGHT {
+            return Err(Error::MaxTotalWeight);
+        }
+    } else if denorm < old_weight {
+        total_weight = c_sub(total_weight, c_sub(old_weight, denorm)?)?;
+        write_total_weight(e, total_weight);
+    }
+
+    record.denorm = denorm;
+
+    let old_balance = record.balance;
+    record.balance = balance;
+
+    if balance > old_balance {
+        pull_underlying(e, &token, admin, c_sub(balance, old_balance)?);
+    } else if balance < old_balance {
+        let token_balance_withdrawn = c_sub(old_balance, balance)?;
+        let token_exit_fee = c_mul(token_balance_withdrawn, 0)?;
+        push_underlying(
+            e,
+            &token,
+            admin,
+            c_sub(token_balance_withdrawn, token_exit_fee)?,
+        );
+        let factory = read_factory(e);
+        push_underlying(e, &token, factory, token_exit_fee)
+    }
+
+    record_map.set(token, record);
+    write_record(e, record_map);
+
+    Ok(())
+}
+
+fn finalize_pool(e: Env, caller: Address, supply: i128, recipient: Address) -> Result<(), Error> {
+    require(!read_finalize(&e), Error::Finalized)?;
+    require(read_tokens(&e).len() > MIN_BOUND_TOKENS, Error::MinTokens)?;
+    // The locked supply can't be more than 1% of what gets minted
+    let min_liquidity = read_min_liquidity(&e);
+    require(min_liquidity <= supply / 100, Error::MaxMinLiquidity)?;
+
+    require_role(&e, &caller, Role::Admin)?;
+    caller.require_auth();
+    write_finalize(&e, true);
+    write_public_swap(&e, true);
+
+    // The minimum liquidity is kept by the pool itself and can never be burned
+    mint_shares(e.clone(), e.current_contract_address(), min_liquidity);
+    mint_shares(e.clone(), recipient.clone(), c_sub(supply, min_liquidity)?);
+    finalize_event(
+        &e,
+        FinalizeEvent {
+            caller,
+            recipient,
+            supply,
+            min_liquidity,
+        },
+    );
+    publish_snapshot(&e, &vec![&e])?;
+
+    Ok(())
+}
+
+// Publishes the pool's balances and total shares, with the prices of `changed` and of any token
+// priced against one of them. Prices are fee-less and quoted in the first bound token.
+fn publish_snapshot(e: &Env, changed: &Vec<Address>) -> Result<(), Error> {
+    let tokens = read_tokens(e);
+    let records = read_record(e);
+    let mut balances: Vec<i128> = vec![e];
+    let mut weights: Vec<i128> = vec![e];
+    let mut prices: Map<Address, i128> = Map::new(e);
+    if let Some(numeraire) = tokens.first() {
+        let numeraire = numeraire.unwrap();
+        let numeraire_record = records.get(numeraire.clone()).unwrap().unwrap();
+        let numeraire_changed = changed.contains(&numeraire);
+        for i in 0..tokens.len() {
+            let t = tokens.get(i).unwrap().unwrap();
+            let rec = records.get(t.clone()).unwrap().unwrap();
+            balances.push_back(rec.balance);
+            weights.push_back(rec.denorm);
+            if t != numeraire && (numeraire_changed || changed.contains(&t)) {
+                let price = calc_spot_price(
+                    numeraire_record.balance,
+                    numeraire_record.denorm,
+                    rec.balance,
+                    rec.denorm,
+                    0,
+                )?;
+                prices.set(t, price);
+            }
+        }
+    }
+
+    snapshot_event(
+        e,
+        SnapshotEvent {
+            tokens,
+            balances,
+            weights,
+            total_shares: get_total_shares(e),
+            prices,
+        },
+    );
+    Ok(())
+}
+
 fn mint_shares(e: Env, to: Address, amount: i128) {
     let total = get_total_shares(&e);
     put_total_shares(&e, total + amount);
@@ -1045,11 +2161,17 @@ fn pull_shares(e: &Env, from: Address, amount: i128) {
     token::Client::new(e, &share_contract_id).xfer(&from, &e.current_contract_address(), &amount);
 }
 
-fn burn_shares(e: &Env, amount: i128) {
+fn burn_shares(e: &Env, amount: i128) -> Result<(), Error> {
     let total = get_total_shares(e);
+    require(
+        c_sub(total, amount)? >= read_min_liquidity(e),
+        Error::MinLiquidity,
+    )?;
     let share_contract_id = get_token_share(e);
     token::Client::new(e, &share_contract_id).burn(&e.current_contract_address(), &amount);
     put_total_shares(e, total - amount);
+
+    Ok(())
 }
 
 fn push_shares(e: &Env, to: Address, amount: i128) {
@@ -1057,8 +2179,268 @@ fn push_shares(e: &Env, to: Address, amount: i128) {
     token::Client::new(e, &share_contract_id).xfer(&e.current_contract_address(), &to, &amount);
 }
 
-fn check_nonnegative_amount(amount: i128) {
-    if amount < 0 {
-        panic!("negative amount is not allowed: {}", amount)
+// Pulls the proportional amount of every token for `pool_amount_out` and mints it to `user`
+fn join_proportional(
+    e: &Env,
+    pool_amount_out: i128,
+    max_amounts_in: &Vec<i128>,
+    user: Address,
+) -> Result<Vec<i128>, Error> {
+    let tokens = read_tokens(e);
+    hooks::before_join(e, &user, &tokens, pool_amount_out, read_swap_fee(e))?;
+
+    let pool_total = get_total_shares(e);
+    let ratio = c_div(pool_amount_out, pool_total)?;
+
+    if ratio == 0 {
+        return Err(Error::MathApprox);
+    }
+    let mut records = read_record(e);
+    let mut amounts_in: Vec<i128> = vec![e];
+    for i in 0..tokens.len() {
+        let t = tokens.get(i).unwrap().unwrap();
+        let mut rec = records.get(t.clone()).unwrap().unwrap();
+        let token_amount_in = c_mul(ratio, rec.balance)?;
+        if token_amount_in == 0 {
+            return Err(Error::MathApprox);
+        }
+
+        if token_amount_in > max_amounts_in.get(i).unwrap().unwrap() {
+            return Err(Error::LimitIn);
+        }
+        rec.balance = c_add(rec.balance, token_amount_in)?;
+        records.set(t.clone(), rec);
+        // emit LOG_JOIN(msg.sender, t, tokenAmountIn);
+        join_event(
+            e,
+            JoinEvent {
+                caller: user.clone(),
+                token_in: t.clone(),
+                token_amount_in: token_amount_in,
+                pool_amount_out,
+            },
+        );
+        pull_underlying(e, &t, user.clone(), token_amount_in);
+        amounts_in.push_back(token_amount_in);
+    }
+
+    write_record(e, records);
+    mint_shares(e.clone(), user.clone(), pool_amount_out);
+    // A proportional join leaves every price where it was
+    publish_snapshot(e, &vec![e])?;
+    hooks::after_join(e, &user, &tokens, &amounts_in, pool_amount_out);
+    Ok(amounts_in)
+}
+
+// Largest pool_amount_out a proportional join can mint without going over any of `max_amounts_in`.
+// Everything is rounded down so the rounding done in join_proportional stays within budget
+fn calc_max_pool_amount_out(
+    e: &Env,
+    records: &Map<Address, Record>,
+    max_amounts_in: &Vec<i128>,
+) -> Result<i128, Error> {
+    let tokens = read_tokens(e);
+    require(max_amounts_in.len() == tokens.len(), Error::TokenCount)?;
+    let mut ratio = i128::MAX;
+    for i in 0..tokens.len() {
+        let rec = records
+            .get(tokens.get(i).unwrap().unwrap())
+            .unwrap()
+            .unwrap();
+        let max_amount_in = max_amounts_in.get(i).unwrap().unwrap();
+        require(max_amount_in >= 0, Error::NegativeAmount)?;
+        let token_ratio = max_amount_in
+            .checked_mul(BONE)
+            .map_or(i128::MAX, |scaled| scaled / rec.balance);
+        ratio = ratio.min(token_ratio);
+    }
+    let pool_amount_out = ratio
+        .checked_mul(get_total_shares(e))
+        .ok_or(Error::MulOverflow)?;
+    Ok(pool_amount_out / BONE)
+}
+
+// Outcome of a zap, every Vec is ordered like the bound tokens.
+// `holdings` is what the user owns after the swaps, before joining
+struct ZapIn {
+    records: Map<Address, Record>,
+    pool_amount_out: i128,
+    swap_amounts_in: Vec<i128>,
+    swap_amounts_out: Vec<i128>,
+    holdings: Vec<i128>,
+    amounts_in: Vec<i128>,
+}
+
+// Swaps part of `token_amount_in` into every other bound token, in proportion to their
+// weights, then joins proportionally with what the swaps produced
+fn calc_zap_in(
+    e: &Env,
+    token_in: &Address,
+    token_amount_in: i128,
+    swap_fee: i128,
+) -> Result<ZapIn, Error> {
+    let tokens = read_tokens(e);
+    let mut records = read_record(e);
+    let total_weight = read_total_weight(e);
+
+    let mut swap_amounts_in: Vec<i128> = vec![e];
+    let mut swap_amounts_out: Vec<i128> = vec![e];
+    let mut holdings: Vec<i128> = vec![e];
+    let mut remaining = token_amount_in;
+    for i in 0..tokens.len() {
+        let t = tokens.get(i).unwrap().unwrap();
+        if t == *token_in {
+            swap_amounts_in.push_back(0);
+            swap_amounts_out.push_back(0);
+            holdings.push_back(0);
+            continue;
+        }
+
+        let mut in_record = records.get(token_in.clone()).unwrap().unwrap();
+        let mut out_record = records.get(t.clone()).unwrap().unwrap();
+        let swap_amount_in = c_mul(token_amount_in, c_div(out_record.denorm, total_weight)?)?;
+        require(
+            swap_amount_in <= c_mul(in_record.balance, MAX_IN_RATIO)?,
+            Error::MaxInRatio,
+        )?;
+        let swap_amount_out = calc_token_out_given_token_in(
+            in_record.balance,
+            in_record.denorm,
+            out_record.balance,
+            out_record.denorm,
+            swap_amount_in,
+            swap_fee,
+        )?;
+        in_record.balance = c_add(in_record.balance, swap_amount_in)?;
+        out_record.balance = c_sub(out_record.balance, swap_amount_out)?;
+        records.set(token_in.clone(), in_record);
+        records.set(t, out_record);
+
+        remaining = c_sub(remaining, swap_amount_in)?;
+        swap_amounts_in.push_back(swap_amount_in);
+        swap_amounts_out.push_back(swap_amount_out);
+        holdings.push_back(swap_amount_out);
     }
+    let index_in = records.get(token_in.clone()).unwrap().unwrap().index;
+    holdings.set(index_in, remaining);
+
+    let pool_amount_out = calc_max_pool_amount_out(e, &records, &holdings)?;
+    let ratio = c_div(pool_amount_out, get_total_shares(e))?;
+    let mut amounts_in: Vec<i128> = vec![e];
+    for i in 0..tokens.len() {
+        let t = tokens.get(i).unwrap().unwrap();
+        let mut rec = records.get(t.clone()).unwrap().unwrap();
+        let token_amount_joined = c_mul(ratio, rec.balance)?;
+        rec.balance = c_add(rec.balance, token_amount_joined)?;
+        records.set(t, rec);
+        amounts_in.push_back(token_amount_joined);
+    }
+
+    Ok(ZapIn {
+        records,
+        pool_amount_out,
+        swap_amounts_in,
+        swap_amounts_out,
+        holdings,
+        amounts_in,
+    })
+}
+
+// LP tokens minted for depositing `amounts_in`, ordered like the bound tokens
+fn calc_join_exact_tokens_in(
+    e: &Env,
+    amounts_in: &Vec<i128>,
+    swap_fee: i128,
+) -> Result<i128, Error> {
+    let tokens = read_tokens(e);
+    require(amounts_in.len() == tokens.len(), Error::TokenCount)?;
+    let records = read_record(e);
+    let mut balances: Vec<i128> = vec![e];
+    let mut weights: Vec<i128> = vec![e];
+    for i in 0..tokens.len() {
+        let rec = records
+            .get(tokens.get(i).unwrap().unwrap())
+            .unwrap()
+            .unwrap();
+        let token_amount_in = amounts_in.get(i).unwrap().unwrap();
+        require(token_amount_in >= 0, Error::NegativeAmount)?;
+        require(
+            token_amount_in <= c_mul(rec.balance, MAX_IN_RATIO)?,
+            Error::MaxInRatio,
+        )?;
+        balances.push_back(rec.balance);
+        weights.push_back(rec.denorm);
+    }
+    calc_lp_token_amount_given_exact_tokens_in(
+        &balances,
+        &weights,
+        amounts_in,
+        get_total_shares(e),
+        read_total_weight(e),
+        swap_fee,
+    )
+}
+
+// LP tokens burned for withdrawing `amounts_out`, ordered like the bound tokens
+fn calc_exit_exact_tokens_out(
+    e: &Env,
+    amounts_out: &Vec<i128>,
+    swap_fee: i128,
+) -> Result<i128, Error> {
+    let tokens = read_tokens(e);
+    require(amounts_out.len() == tokens.len(), Error::TokenCount)?;
+    let records = read_record(e);
+    let mut balances: Vec<i128> = vec![e];
+    let mut weights: Vec<i128> = vec![e];
+    for i in 0..tokens.len() {
+        let rec = records
+            .get(tokens.get(i).unwrap().unwrap())
+            .unwrap()
+            .unwrap();
+        let token_amount_out = amounts_out.get(i).unwrap().unwrap();
+        require(token_amount_out >= 0, Error::NegativeAmount)?;
+        require(
+            token_amount_out <= c_mul(rec.balance, MAX_OUT_RATIO)?,
+            Error::MaxOutRatio,
+        )?;
+        balances.push_back(rec.balance);
+        weights.push_back(rec.denorm);
+    }
+    calc_lp_token_amount_given_exact_tokens_out(
+        &balances,
+        &weights,
+        amounts_out,
+        get_total_shares(e),
+        read_total_weight(e),
+        swap_fee,
+    )
+}
+
+fn check_referral(e: &Env, referrer: &Option<Address>, referral_fee: i128) -> Result<(), Error> {
+    require(referral_fee >= 0, Error::MinReferralFee)?;
+    require(referrer.is_some() || referral_fee == 0, Error::NoReferrer)?;
+    require(
+        referral_fee <= read_max_referral_fee(e),
+        Error::MaxReferralFee,
+    )
+}
+
+// Swap fee charged to `user`, after applying any discount set by the controller
+fn effective_swap_fee(e: &Env, user: Address, base_fee: i128) -> Result<i128, Error> {
+    c_mul(base_fee, read_fee_multiplier(e, user))
+}
+
+// Fee on a zap's swaps, set by the hooks and then discounted for `user`
+fn zap_swap_fee(
+    e: &Env,
+    user: &Address,
+    tokens: &Vec<Address>,
+    min_pool_amount_out: i128,
+) -> Result<i128, Error> {
+    let base_fee = hooks::before_join(e, user, tokens, min_pool_amount_out, read_swap_fee(e))?;
+    effective_swap_fee(e, user.clone(), base_fee)
+}
+
+fn check_nonnegative_amount(amount: i128) -> Result<(), Error> {
+    require(amount >= 0, Error::NegativeAmount)
 }
@@ -0,0 +1,125 @@
+use soroban_sdk::{Address, Env, Map, Vec};
+
+use crate::{
+    c_consts::{BONE, FEE_PER_SHARE_PRECISION},
+    c_errors::Error,
+    c_num::{c_div, c_mul, c_sub},
+};
+
+use super::{
+    metadata::{get_total_shares, read_tokens},
+    storage_types::{DataKey, FeeCheckpoint},
+};
+
+// Read the swap fees collected per LP token, by token
+pub fn read_fee_per_share(e: &Env) -> Map<Address, i128> {
+    e.storage()
+        .get(&DataKey::FeePerShare)
+        .unwrap_or(Ok(Map::new(e)))
+        .unwrap()
+}
+
+// Write the swap fees collected per LP token
+fn write_fee_per_share(e: &Env, fee_per_share: Map<Address, i128>) {
+    e.storage().set(&DataKey::FeePerShare, &fee_per_share);
+}
+
+// Read an LP's last fee checkpoint
+pub fn read_fee_checkpoint(e: &Env, lp: Address) -> FeeCheckpoint {
+    e.storage()
+        .get(&DataKey::FeeCheckpoint(lp))
+        .unwrap_or(Ok(FeeCheckpoint {
+            fee_per_share: Map::new(e),
+            earned: Map::new(e),
+        }))
+        .unwrap()
+}
+
+// Write an LP's fee checkpoint
+fn write_fee_checkpoint(e: &Env, lp: Address, checkpoint: FeeCheckpoint) {
+    e.storage().set(&DataKey::FeeCheckpoint(lp), &checkpoint);
+}
+
+// Spreads a swap fee paid in `token` over the LP tokens currently in circulation
+pub fn accrue_fee(e: &Env, token: Address, fee: i128) -> Result<(), Error> {
+    let total_shares = get_total_shares(e);
+    if fee <= 0 || total_shares == 0 {
+        return Ok(());
+    }
+    let mut fee_per_share = read_fee_per_share(e);
+    let current = fee_per_share
+        .get(token.clone())
+        .map_or(0, |acc| acc.unwrap());
+    let accrued = fee
+        .checked_mul(FEE_PER_SHARE_PRECISION)
+        .ok_or(Error::MulOverflow)?
+        / total_shares;
+    fee_per_share.set(
+        token,
+        current.checked_add(accrued).ok_or(Error::AddOverflow)?,
+    );
+    write_fee_per_share(e, fee_per_share);
+
+    Ok(())
+}
+
+// Settles what `balance` earned since the LP's last checkpoint and moves the checkpoint to now.
+// Must run before the LP's balance changes.
+pub fn checkpoint(e: &Env, lp: Address, balance: i128) {
+    let fee_per_share = read_fee_per_share(e);
+    let last = read_fee_checkpoint(e, lp.clone());
+    let mut earned = last.earned.clone();
+    for entry in fee_per_share.iter() {
+        let (token, acc) = entry.unwrap();
+        let accrued = accrued_since(&last, token.clone(), acc, balance);
+        if accrued != 0 {
+            let previous = earned.get(token.clone()).map_or(0, |fees| fees.unwrap());
+            earned.set(token, previous + accrued);
+        }
+    }
+    write_fee_checkpoint(
+        e,
+        lp,
+        FeeCheckpoint {
+            fee_per_share,
+            earned,
+        },
+    );
+}
+
+// Swap fees earned by an LP, ordered like the bound tokens
+pub fn fees_earned(e: &Env, lp: Address, balance: i128) -> Vec<i128> {
+    let fee_per_share = read_fee_per_share(e);
+    let last = read_fee_checkpoint(e, lp);
+    let tokens = read_tokens(e);
+    let mut fees: Vec<i128> = Vec::new(e);
+    for token in tokens.iter() {
+        let token = token.unwrap();
+        let acc = fee_per_share
+            .get(token.clone())
+            .map_or(0, |acc| acc.unwrap());
+        let settled = last
+            .earned
+            .get(token.clone())
+            .map_or(0, |fees| fees.unwrap());
+        fees.push_back(settled + accrued_since(&last, token, acc, balance));
+    }
+    fees
+}
+
+// Part of a single sided deposit or withdrawal that is charged the swap fee, the same split c_math uses
+pub fn single_sided_fee(
+    amount: i128,
+    denorm: i128,
+    total_weight: i128,
+    swap_fee: i128,
+) -> Result<i128, Error> {
+    let normalized_weight = c_div(denorm, total_weight)?;
+    let taxable = c_mul(amount, c_sub(BONE, normalized_weight)?)?;
+    c_mul(taxable, swap_fee)
+}
+
+fn accrued_since(last: &FeeCheckpoint, token: Address, acc: i128, balance: i128) -> i128 {
+    let last_acc = last.fee_per_share.get(token).map_or(0, |acc| acc.unwrap());
+    balance * (acc - last_acc) / FEE_PER_SHARE_PRECISION
+}
@@ -1,4 +1,6 @@
-use soroban_sdk::{contracttype, Address, Env, Symbol};
+use soroban_sdk::{contracttype, Address, Env, Map, Symbol, Vec};
+
+use super::{metadata::read_legacy_events, roles::Role};
 
 #[contracttype]
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -8,6 +10,8 @@ pub struct SwapEvent {
     pub token_out: Address,
     pub token_amount_in: i128,
     pub token_amount_out: i128,
+    pub referrer: Option<Address>,
+    pub referral_amount: i128,
 }
 
 #[contracttype]
@@ -16,6 +20,7 @@ pub struct JoinEvent {
     pub caller: Address,
     pub token_in: Address,
     pub token_amount_in: i128,
+    pub pool_amount_out: i128, // LP shares minted by the whole join, repeated for each token
 }
 
 #[contracttype]
@@ -24,22 +29,340 @@ pub struct ExitEvent {
     pub caller: Address,
     pub token_out: Address,
     pub token_amount_out: i128,
+    pub pool_amount_in: i128, // LP shares paid in by the whole exit, exit fee included
 }
 
+// Swap, join and exit bodies as published before the V1 topics, kept for the legacy event format
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct LegacySwapEvent {
+    pub caller: Address,
+    pub token_in: Address,
+    pub token_out: Address,
+    pub token_amount_in: i128,
+    pub token_amount_out: i128,
+}
 
-// Token Events 
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct LegacyJoinEvent {
+    pub caller: Address,
+    pub token_in: Address,
+    pub token_amount_in: i128,
+}
 
-pub fn incr_allow_event(e: &Env, from: Address, to: Address, amount: i128) {
-    let topics = (Symbol::new(e, "incr_allow"), from, to);
-    e.events().publish(topics, amount);
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct LegacyExitEvent {
+    pub caller: Address,
+    pub token_out: Address,
+    pub token_amount_out: i128,
 }
 
+// Pool state after a swap, join, exit, bind or rebind, so indexers can start from any point
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SnapshotEvent {
+    pub tokens: Vec<Address>,
+    pub balances: Vec<i128>, // record balance of each token in `tokens`
+    pub weights: Vec<i128>,  // denormalized weight of each token in `tokens`
+    pub total_shares: i128,
+    pub prices: Map<Address, i128>, // fee-less spot prices in the first token, of those that moved
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BindEvent {
+    pub caller: Address,
+    pub token: Address,
+    pub balance: i128,
+    pub denorm: i128,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RebindEvent {
+    pub caller: Address,
+    pub token: Address,
+    pub old_balance: i128,
+    pub new_balance: i128,
+    pub old_denorm: i128,
+    pub new_denorm: i128,
+}
 
-pub fn decr_allow_event(e: &Env, from: Address, to: Address, amount: i128) {
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FinalizeEvent {
+    pub caller: Address,
+    pub recipient: Address,
+    pub supply: i128,
+    pub min_liquidity: i128,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SwapFeeEvent {
+    pub caller: Address,
+    pub old_fee: i128,
+    pub new_fee: i128,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ControllerEvent {
+    pub caller: Address,
+    pub old_controller: Address,
+    pub new_controller: Address,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PublicSwapEvent {
+    pub caller: Address,
+    pub old_value: bool,
+    pub new_value: bool,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct LegacyEventsEvent {
+    pub caller: Address,
+    pub old_value: bool,
+    pub new_value: bool,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MinLiquidityEvent {
+    pub caller: Address,
+    pub old_amount: i128,
+    pub new_amount: i128,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MaxReferralFeeEvent {
+    pub caller: Address,
+    pub old_fee: i128,
+    pub new_fee: i128,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FeeMultiplierEvent {
+    pub caller: Address,
+    pub user: Address,
+    pub old_multiplier: i128,
+    pub new_multiplier: i128,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FeeRampEvent {
+    pub caller: Address,
+    pub start_fee: i128,
+    pub target_fee: i128,
+    pub start_time: u64,
+    pub end_time: u64,
+}
+
+// Swaps, joins and exits are published under versioned topics that carry the caller and tokens,
+// (SWAP_V1, caller, token_in, token_out), (JOIN_V1, caller, token_in) and (EXIT_V1, caller, token_out).
+// With legacy events on they go out as ("LOG", <action>) with the pre-V1 bodies instead.
+
+pub fn swap_event(e: &Env, event: SwapEvent) {
+    if read_legacy_events(e) {
+        let legacy = LegacySwapEvent {
+            caller: event.caller,
+            token_in: event.token_in,
+            token_out: event.token_out,
+            token_amount_in: event.token_amount_in,
+            token_amount_out: event.token_amount_out,
+        };
+        e.events()
+            .publish((Symbol::short("LOG"), Symbol::short("SWAP")), legacy);
+    } else {
+        let topics = (
+            Symbol::short("SWAP_V1"),
+            event.caller.clone(),
+            event.token_in.clone(),
+            event.token_out.clone(),
+        );
+        e.events().publish(topics, event);
+    }
+}
+
+pub fn join_event(e: &Env, event: JoinEvent) {
+    if read_legacy_events(e) {
+        let legacy = LegacyJoinEvent {
+            caller: event.caller,
+            token_in: event.token_in,
+            token_amount_in: event.token_amount_in,
+        };
+        e.events()
+            .publish((Symbol::short("LOG"), Symbol::short("JOIN")), legacy);
+    } else {
+        let topics = (
+            Symbol::short("JOIN_V1"),
+            event.caller.clone(),
+            event.token_in.clone(),
+        );
+        e.events().publish(topics, event);
+    }
+}
+
+pub fn exit_event(e: &Env, event: ExitEvent) {
+    if read_legacy_events(e) {
+        let legacy = LegacyExitEvent {
+            caller: event.caller,
+            token_out: event.token_out,
+            token_amount_out: event.token_amount_out,
+        };
+        e.events()
+            .publish((Symbol::short("LOG"), Symbol::short("EXIT")), legacy);
+    } else {
+        let topics = (
+            Symbol::short("EXIT_V1"),
+            event.caller.clone(),
+            event.token_out.clone(),
+        );
+        e.events().publish(topics, event);
+    }
+}
+
+pub fn snapshot_event(e: &Env, event: SnapshotEvent) {
+    e.events().publish((Symbol::short("SNAP_V1"),), event);
+}
+
+// Pool configuration events, published under ("LOG", <SETTING>) with the caller and the old and
+// new values. Governance, roles, rescues and delegation are actions of one account, they use a
+// lowercase name followed by the addresses involved like the token interface events.
+
+pub fn bind_event(e: &Env, event: BindEvent) {
+    e.events()
+        .publish((Symbol::short("LOG"), Symbol::short("BIND")), event);
+}
+
+pub fn rebind_event(e: &Env, event: RebindEvent) {
+    e.events()
+        .publish((Symbol::short("LOG"), Symbol::short("REBIND")), event);
+}
+
+pub fn finalize_event(e: &Env, event: FinalizeEvent) {
+    e.events()
+        .publish((Symbol::short("LOG"), Symbol::short("FINALIZE")), event);
+}
+
+pub fn swap_fee_event(e: &Env, event: SwapFeeEvent) {
+    e.events()
+        .publish((Symbol::short("LOG"), Symbol::short("SWAP_FEE")), event);
+}
+
+pub fn controller_event(e: &Env, event: ControllerEvent) {
+    e.events()
+        .publish((Symbol::short("LOG"), Symbol::new(e, "CONTROLLER")), event);
+}
+
+pub fn public_swap_event(e: &Env, event: PublicSwapEvent) {
+    e.events()
+        .publish((Symbol::short("LOG"), Symbol::new(e, "PUBLIC_SWAP")), event);
+}
+
+pub fn legacy_events_event(e: &Env, event: LegacyEventsEvent) {
+    e.events().publish(
+        (Symbol::short("LOG"), Symbol::new(e, "LEGACY_EVENTS")),
+        event,
+    );
+}
+
+pub fn min_liquidity_event(e: &Env, event: MinLiquidityEvent) {
+    e.events().publish(
+        (Symbol::short("LOG"), Symbol::new(e, "MIN_LIQUIDITY")),
+        event,
+    );
+}
+
+pub fn max_referral_fee_event(e: &Env, event: MaxReferralFeeEvent) {
+    e.events().publish(
+        (Symbol::short("LOG"), Symbol::new(e, "MAX_REFERRAL_FEE")),
+        event,
+    );
+}
+
+pub fn fee_multiplier_event(e: &Env, event: FeeMultiplierEvent) {
+    e.events().publish(
+        (Symbol::short("LOG"), Symbol::new(e, "FEE_MULTIPLIER")),
+        event,
+    );
+}
+
+pub fn fee_ramp_event(e: &Env, event: FeeRampEvent) {
+    e.events()
+        .publish((Symbol::short("LOG"), Symbol::short("FEE_RAMP")), event);
+}
+
+pub fn fee_proposal_event(e: &Env, proposer: Address, id: u32, fee: i128) {
+    let topics = (Symbol::new(e, "fee_proposal"), proposer, id);
+    e.events().publish(topics, fee);
+}
+
+pub fn fee_vote_event(e: &Env, voter: Address, id: u32, support: bool, weight: i128) {
+    let topics = (Symbol::short("fee_vote"), voter, id);
+    e.events().publish(topics, (support, weight));
+}
+
+pub fn fee_proposal_executed_event(e: &Env, id: u32, fee: i128) {
+    let topics = (Symbol::new(e, "fee_proposal_executed"), id);
+    e.events().publish(topics, fee);
+}
+
+pub fn role_granted_event(e: &Env, role: Role, account: Address, caller: Address) {
+    let topics = (Symbol::new(e, "role_granted"), role, account);
+    e.events().publish(topics, caller);
+}
+
+pub fn role_revoked_event(e: &Env, role: Role, account: Address, caller: Address) {
+    let topics = (Symbol::new(e, "role_revoked"), role, account);
+    e.events().publish(topics, caller);
+}
+
+pub fn rescue_event(e: &Env, caller: Address, token: Address, to: Address, amount: i128) {
+    let topics = (Symbol::short("rescue"), caller, token);
+    e.events().publish(topics, (to, amount));
+}
+
+// Token Events
+
+pub fn incr_allow_event(e: &Env, from: Address, to: Address, amount: i128, expiration_ledger: u32) {
+    let topics = (Symbol::new(e, "incr_allow"), from, to);
+    e.events().publish(topics, (amount, expiration_ledger));
+}
+
+pub fn decr_allow_event(e: &Env, from: Address, to: Address, amount: i128, expiration_ledger: u32) {
     let topics = (Symbol::new(e, "decr_allow"), from, to);
+    e.events().publish(topics, (amount, expiration_ledger));
+}
+
+pub fn approve_event(e: &Env, from: Address, to: Address, amount: i128, expiration_ledger: u32) {
+    let topics = (Symbol::short("approve"), from, to);
+    e.events().publish(topics, (amount, expiration_ledger));
+}
+
+pub fn permit_event(e: &Env, owner: Address, spender: Address, amount: i128) {
+    let topics = (Symbol::short("permit"), owner, spender);
     e.events().publish(topics, amount);
 }
 
+pub fn delegate_event(e: &Env, delegator: Address, from: Option<Address>, to: Address) {
+    let topics = (Symbol::short("delegate"), delegator);
+    e.events().publish(topics, (from, to));
+}
+
+pub fn delegate_votes_event(e: &Env, delegatee: Address, previous: i128, votes: i128) {
+    let topics = (Symbol::short("votes"), delegatee);
+    e.events().publish(topics, (previous, votes));
+}
+
 pub fn transfer_event(e: &Env, from: Address, to: Address, amount: i128) {
     let topics = (Symbol::short("transfer"), from, to);
     e.events().publish(topics, amount);
@@ -69,4 +392,3 @@ pub fn burn_event(e: &Env, from: Address, amount: i128) {
     let topics = (Symbol::short("burn"), from);
     e.events().publish(topics, amount);
 }
-
@@ -0,0 +1,154 @@
+use soroban_sdk::{contracttype, Address, Env};
+
+use crate::{
+    c_consts::{
+        BONE, GOV_EXECUTION_DELAY, GOV_EXECUTION_WINDOW, GOV_PROPOSAL_THRESHOLD, GOV_QUORUM,
+        GOV_VOTING_PERIOD, MAX_FEE, MIN_FEE,
+    },
+    c_errors::{require, Error},
+    c_num::c_mul,
+};
+
+use super::{
+    checkpoints::{balance_at, total_supply_at},
+    events::{fee_proposal_event, fee_proposal_executed_event, fee_vote_event},
+    metadata::{read_finalize, read_swap_fee_version, write_swap_fee},
+    storage_types::DataKey,
+    votes::votes_at,
+};
+
+// A swap fee change put to the LPs, weighted by their delegated votes at `snapshot_ledger`
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FeeProposal {
+    pub proposer: Address,
+    pub fee: i128,
+    pub snapshot_ledger: u32,
+    pub end_ledger: u32, // last ledger votes are accepted in
+    pub votes_for: i128,
+    pub votes_against: i128,
+    pub executed: bool,
+    pub fee_version: u32, // swap fee version when proposed, any later fee change voids the proposal
+}
+
+// Read the number of fee proposals made so far, also the next proposal id
+pub fn read_proposal_count(e: &Env) -> u32 {
+    e.storage()
+        .get(&DataKey::FeeProposalCount)
+        .unwrap_or(Ok(0))
+        .unwrap()
+}
+
+// Read a fee proposal
+pub fn read_proposal(e: &Env, id: u32) -> Result<FeeProposal, Error> {
+    Ok(e.storage()
+        .get(&DataKey::FeeProposal(id))
+        .ok_or(Error::NoProposal)?
+        .unwrap())
+}
+
+fn write_proposal(e: &Env, id: u32, proposal: FeeProposal) {
+    e.storage().set(&DataKey::FeeProposal(id), &proposal);
+}
+
+fn has_voted(e: &Env, id: u32, voter: Address) -> bool {
+    e.storage().has(&DataKey::FeeVote(id, voter))
+}
+
+fn write_voted(e: &Env, id: u32, voter: Address) {
+    e.storage().set(&DataKey::FeeVote(id, voter), &true);
+}
+
+// LP supply that can vote at `ledger`, the shares locked in the pool itself never do
+fn voting_supply_at(e: &Env, ledger: u32) -> i128 {
+    total_supply_at(e, ledger) - balance_at(e, e.current_contract_address(), ledger)
+}
+
+pub fn propose(e: &Env, proposer: Address, fee: i128) -> Result<u32, Error> {
+    require(read_finalize(e), Error::NotFinalized)?;
+    require(fee >= MIN_FEE, Error::MinFee)?;
+    require(fee <= MAX_FEE, Error::MaxFee)?;
+
+    // Balances from the previous ledger, so shares bought for the vote don't count
+    let snapshot_ledger = e.ledger().sequence().saturating_sub(1);
+    let threshold = c_mul(total_supply_at(e, snapshot_ledger), GOV_PROPOSAL_THRESHOLD)?;
+    require(
+        balance_at(e, proposer.clone(), snapshot_ledger) >= threshold,
+        Error::ProposalThreshold,
+    )?;
+
+    let id = read_proposal_count(e);
+    e.storage().set(&DataKey::FeeProposalCount, &(id + 1));
+    write_proposal(
+        e,
+        id,
+        FeeProposal {
+            proposer: proposer.clone(),
+            fee,
+            snapshot_ledger,
+            end_ledger: e.ledger().sequence() + GOV_VOTING_PERIOD,
+            votes_for: 0,
+            votes_against: 0,
+            executed: false,
+            fee_version: read_swap_fee_version(e),
+        },
+    );
+    fee_proposal_event(e, proposer, id, fee);
+    Ok(id)
+}
+
+pub fn vote(e: &Env, voter: Address, id: u32, support: bool) -> Result<(), Error> {
+    let mut proposal = read_proposal(e, id)?;
+    require(
+        e.ledger().sequence() <= proposal.end_ledger,
+        Error::VotingClosed,
+    )?;
+    require(!has_voted(e, id, voter.clone()), Error::AlreadyVoted)?;
+
+    let weight = votes_at(e, voter.clone(), proposal.snapshot_ledger);
+    require(weight > 0, Error::NoVotingPower)?;
+    if support {
+        proposal.votes_for += weight;
+    } else {
+        proposal.votes_against += weight;
+    }
+    write_voted(e, id, voter.clone());
+    write_proposal(e, id, proposal);
+    fee_vote_event(e, voter, id, support, weight);
+
+    Ok(())
+}
+
+// The only way LPs change the fee of a finalized pool, after the vote passed and the delay ran out,
+// and only until the execution window closes
+pub fn execute(e: &Env, id: u32) -> Result<(), Error> {
+    let mut proposal = read_proposal(e, id)?;
+    require(!proposal.executed, Error::Executed)?;
+    require(
+        e.ledger().sequence() > proposal.end_ledger + GOV_EXECUTION_DELAY,
+        Error::Timelock,
+    )?;
+    require(
+        e.ledger().sequence() <= proposal.end_ledger + GOV_EXECUTION_DELAY + GOV_EXECUTION_WINDOW,
+        Error::Expired,
+    )?;
+    // A ramp or another proposal moved the fee since the vote started
+    require(
+        proposal.fee_version == read_swap_fee_version(e),
+        Error::FeeChanged,
+    )?;
+
+    let quorum = c_mul(voting_supply_at(e, proposal.snapshot_ledger), GOV_QUORUM)?;
+    require(
+        proposal.votes_for + proposal.votes_against >= quorum,
+        Error::Quorum,
+    )?;
+    require(proposal.votes_for > proposal.votes_against, Error::Defeated)?;
+
+    proposal.executed = true;
+    write_swap_fee(e, proposal.fee);
+    write_proposal(e, id, proposal.clone());
+    fee_proposal_executed_event(e, id, proposal.fee);
+
+    Ok(())
+}
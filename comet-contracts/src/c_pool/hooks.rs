@@ -0,0 +1,211 @@
+// Optional hooks contract called around swaps, joins and exits
+use soroban_sdk::{contractclient, contracttype, Address, Env, Vec};
+
+use super::metadata::read_hooks;
+use crate::{
+    c_consts::{MAX_FEE, MIN_FEE},
+    c_errors::{require, Error},
+};
+
+// Returned by every `before_*` hook. `fee` is the swap fee the pool should use,
+// a hook that doesn't want to change it returns the fee it was given
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct HookDecision {
+    pub allow: bool,
+    pub fee: i128,
+}
+
+// Interface a hooks contract has to implement.
+// For joins and exits `pool_amount` is the LP amount requested by the user,
+// or its limit when the final amount depends on the fee
+#[contractclient(name = "HooksClient")]
+pub trait HooksInterface {
+    #[allow(clippy::too_many_arguments)]
+    fn before_swap(
+        e: Env,
+        pool: Address,
+        user: Address,
+        token_in: Address,
+        token_out: Address,
+        token_amount: i128,
+        exact_in: bool,
+        fee: i128,
+    ) -> HookDecision;
+
+    fn after_swap(
+        e: Env,
+        pool: Address,
+        user: Address,
+        token_in: Address,
+        token_out: Address,
+        token_amount_in: i128,
+        token_amount_out: i128,
+    );
+
+    fn before_join(
+        e: Env,
+        pool: Address,
+        user: Address,
+        tokens_in: Vec<Address>,
+        pool_amount: i128,
+        fee: i128,
+    ) -> HookDecision;
+
+    fn after_join(
+        e: Env,
+        pool: Address,
+        user: Address,
+        tokens_in: Vec<Address>,
+        token_amounts_in: Vec<i128>,
+        pool_amount_out: i128,
+    );
+
+    fn before_exit(
+        e: Env,
+        pool: Address,
+        user: Address,
+        tokens_out: Vec<Address>,
+        pool_amount: i128,
+        fee: i128,
+    ) -> HookDecision;
+
+    fn after_exit(
+        e: Env,
+        pool: Address,
+        user: Address,
+        tokens_out: Vec<Address>,
+        token_amounts_out: Vec<i128>,
+        pool_amount_in: i128,
+    );
+}
+
+fn hooks_client(e: &Env) -> Option<HooksClient> {
+    read_hooks(e).map(|hooks| HooksClient::new(e, &hooks.contract_id().unwrap()))
+}
+
+// Fails when the hook vetoes, otherwise returns the fee to use
+fn check_decision(decision: HookDecision) -> Result<i128, Error> {
+    require(decision.allow, Error::HookVeto)?;
+    require(decision.fee >= MIN_FEE, Error::HookMinFee)?;
+    require(decision.fee <= MAX_FEE, Error::HookMaxFee)?;
+    Ok(decision.fee)
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn before_swap(
+    e: &Env,
+    user: &Address,
+    token_in: &Address,
+    token_out: &Address,
+    token_amount: i128,
+    exact_in: bool,
+    fee: i128,
+) -> Result<i128, Error> {
+    match hooks_client(e) {
+        Some(client) => check_decision(client.before_swap(
+            &e.current_contract_address(),
+            user,
+            token_in,
+            token_out,
+            &token_amount,
+            &exact_in,
+            &fee,
+        )),
+        None => Ok(fee),
+    }
+}
+
+pub fn after_swap(
+    e: &Env,
+    user: &Address,
+    token_in: &Address,
+    token_out: &Address,
+    token_amount_in: i128,
+    token_amount_out: i128,
+) {
+    if let Some(client) = hooks_client(e) {
+        client.after_swap(
+            &e.current_contract_address(),
+            user,
+            token_in,
+            token_out,
+            &token_amount_in,
+            &token_amount_out,
+        );
+    }
+}
+
+pub fn before_join(
+    e: &Env,
+    user: &Address,
+    tokens_in: &Vec<Address>,
+    pool_amount: i128,
+    fee: i128,
+) -> Result<i128, Error> {
+    match hooks_client(e) {
+        Some(client) => check_decision(client.before_join(
+            &e.current_contract_address(),
+            user,
+            tokens_in,
+            &pool_amount,
+            &fee,
+        )),
+        None => Ok(fee),
+    }
+}
+
+pub fn after_join(
+    e: &Env,
+    user: &Address,
+    tokens_in: &Vec<Address>,
+    token_amounts_in: &Vec<i128>,
+    pool_amount_out: i128,
+) {
+    if let Some(client) = hooks_client(e) {
+        client.after_join(
+            &e.current_contract_address(),
+            user,
+            tokens_in,
+            token_amounts_in,
+            &pool_amount_out,
+        );
+    }
+}
+
+pub fn before_exit(
+    e: &Env,
+    user: &Address,
+    tokens_out: &Vec<Address>,
+    pool_amount: i128,
+    fee: i128,
+) -> Result<i128, Error> {
+    match hooks_client(e) {
+        Some(client) => check_decision(client.before_exit(
+            &e.current_contract_address(),
+            user,
+            tokens_out,
+            &pool_amount,
+            &fee,
+        )),
+        None => Ok(fee),
+    }
+}
+
+pub fn after_exit(
+    e: &Env,
+    user: &Address,
+    tokens_out: &Vec<Address>,
+    token_amounts_out: &Vec<i128>,
+    pool_amount_in: i128,
+) {
+    if let Some(client) = hooks_client(e) {
+        client.after_exit(
+            &e.current_contract_address(),
+            user,
+            tokens_out,
+            token_amounts_out,
+            &pool_amount_in,
+        );
+    }
+}
@@ -1,7 +1,13 @@
-use crate::c_pool::storage_types::DataKey;
+use crate::{
+    c_consts::{BONE, MIN_LIQUIDITY},
+    c_pool::storage_types::DataKey,
+};
 use soroban_sdk::{vec, Address, Bytes, BytesN, Env, Map, Vec};
 
-use super::storage_types::{Record, DataKeyToken};
+use super::{
+    checkpoints::write_supply_checkpoint,
+    storage_types::{DataKeyToken, FeeRamp, Record},
+};
 
 // Read Tokens Vec
 pub fn read_tokens(e: &Env) -> Vec<Address> {
@@ -55,22 +61,117 @@ pub fn write_controller(e: &Env, d: Address) {
     e.storage().set(&key, &d)
 }
 
-// Read Swap Fee
+// Read Hooks
+pub fn read_hooks(e: &Env) -> Option<Address> {
+    let key = DataKey::Hooks;
+    e.storage().get(&key).map(|hooks| hooks.unwrap())
+}
+
+// Write Hooks
+pub fn write_hooks(e: &Env, d: Address) {
+    let key = DataKey::Hooks;
+    e.storage().set(&key, &d)
+}
+
+// Read Swap Fee, interpolated along the fee ramp if there is one
 pub fn read_swap_fee(e: &Env) -> i128 {
-    let key = DataKey::SwapFee;
+    if let Some(ramp) = read_swap_fee_ramp(e) {
+        let now = e.ledger().timestamp();
+        if now >= ramp.end_time {
+            return ramp.target_fee;
+        }
+        let elapsed = i128::from(now - ramp.start_time);
+        let duration = i128::from(ramp.end_time - ramp.start_time);
+        return ramp.start_fee + (ramp.target_fee - ramp.start_fee) * elapsed / duration;
+    }
 
+    let key = DataKey::SwapFee;
     e.storage()
         .get(&key)
         .unwrap_or(Ok(0)) // if no members on vector
         .unwrap()
 }
 
-// Write Swap Fee
+// Write Swap Fee, cancels any fee ramp
 pub fn write_swap_fee(e: &Env, d: i128) {
     let key = DataKey::SwapFee;
+    e.storage().set(&key, &d);
+    e.storage().remove(&DataKey::SwapFeeRamp);
+    bump_swap_fee_version(e);
+}
+
+// Read Swap Fee Version, fee proposals made before the last change can't be executed
+pub fn read_swap_fee_version(e: &Env) -> u32 {
+    let key = DataKey::SwapFeeVersion;
+    e.storage().get(&key).unwrap_or(Ok(0)).unwrap()
+}
+
+fn bump_swap_fee_version(e: &Env) {
+    let key = DataKey::SwapFeeVersion;
+    e.storage().set(&key, &(read_swap_fee_version(e) + 1));
+}
+
+// Read Swap Fee Ramp
+pub fn read_swap_fee_ramp(e: &Env) -> Option<FeeRamp> {
+    let key = DataKey::SwapFeeRamp;
+    e.storage().get(&key).map(|ramp| ramp.unwrap())
+}
+
+// Write Swap Fee Ramp, the target also becomes the stored fee for when the ramp is over
+pub fn write_swap_fee_ramp(e: &Env, ramp: FeeRamp) {
+    e.storage().set(&DataKey::SwapFee, &ramp.target_fee);
+    e.storage().set(&DataKey::SwapFeeRamp, &ramp);
+    bump_swap_fee_version(e);
+}
+
+// Read Fee Multiplier
+pub fn read_fee_multiplier(e: &Env, user: Address) -> i128 {
+    let key = DataKey::FeeMultiplier(user);
+    e.storage().get(&key).unwrap_or(Ok(BONE)).unwrap()
+}
+
+// Write Fee Multiplier
+pub fn write_fee_multiplier(e: &Env, user: Address, multiplier: i128) {
+    let key = DataKey::FeeMultiplier(user);
+    e.storage().set(&key, &multiplier)
+}
+
+// Read Max Referral Fee
+pub fn read_max_referral_fee(e: &Env) -> i128 {
+    let key = DataKey::MaxReferralFee;
+    e.storage().get(&key).unwrap_or(Ok(0)).unwrap()
+}
+
+// Write Max Referral Fee
+pub fn write_max_referral_fee(e: &Env, d: i128) {
+    let key = DataKey::MaxReferralFee;
+    e.storage().set(&key, &d)
+}
+
+// Read Min Liquidity
+pub fn read_min_liquidity(e: &Env) -> i128 {
+    let key = DataKey::MinLiquidity;
+    e.storage().get(&key).unwrap_or(Ok(MIN_LIQUIDITY)).unwrap()
+}
+
+// Write Min Liquidity
+pub fn write_min_liquidity(e: &Env, d: i128) {
+    let key = DataKey::MinLiquidity;
     e.storage().set(&key, &d)
 }
 
+// Read Legacy Events
+pub fn read_legacy_events(e: &Env) -> bool {
+    let key = DataKey::LegacyEvents;
+    e.storage().get(&key).unwrap_or(Ok(false)).unwrap()
+}
+
+// Write Legacy Events
+pub fn write_legacy_events(e: &Env, val: bool) {
+    let key = DataKey::LegacyEvents;
+    e.storage().set(&key, &val)
+}
+
 // Read Total Weight
 pub fn read_total_weight(e: &Env) -> i128 {
     let key = DataKey::TotalWeight;
@@ -103,7 +204,8 @@ pub fn get_total_shares(e: &Env) -> i128 {
 
 // Update Total Shares
 pub fn put_total_shares(e: &Env, amount: i128) {
-    e.storage().set(&DataKey::TotalShares, &amount)
+    e.storage().set(&DataKey::TotalShares, &amount);
+    write_supply_checkpoint(e, amount);
 }
 
 // Read Finalize
@@ -5,3 +5,10 @@ pub mod storage_types;
 pub mod admin;
 pub mod balance;
 pub mod allowance;
+pub mod hooks;
+pub mod earnings;
+pub mod permit;
+pub mod checkpoints;
+pub mod votes;
+pub mod governance;
+pub mod roles;
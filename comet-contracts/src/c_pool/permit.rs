@@ -0,0 +1,58 @@
+use soroban_sdk::{contracttype, serde::Serialize, Address, BytesN, Env};
+
+use crate::c_errors::{require, Error};
+
+use super::storage_types::DataKeyToken;
+
+// What the owner signs to approve `spender` without sending a transaction
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PermitPayload {
+    pub token: Address, // the pool, so a signature can't be replayed on another pool
+    pub owner: Address,
+    pub spender: Address,
+    pub amount: i128,
+    pub nonce: i128,
+    pub expiry: u32, // last ledger sequence the permit can be used in
+}
+
+pub fn read_nonce(e: &Env, owner: Address) -> i128 {
+    let key = DataKeyToken::Nonce(owner);
+    if let Some(nonce) = e.storage().get(&key) {
+        nonce.unwrap()
+    } else {
+        0
+    }
+}
+
+fn write_nonce(e: &Env, owner: Address, nonce: i128) {
+    let key = DataKeyToken::Nonce(owner);
+    e.storage().set(&key, &nonce);
+}
+
+pub fn read_permit_key(e: &Env, owner: Address) -> Option<BytesN<32>> {
+    let key = DataKeyToken::PermitKey(owner);
+    e.storage().get(&key).map(|public_key| public_key.unwrap())
+}
+
+pub fn write_permit_key(e: &Env, owner: Address, public_key: BytesN<32>) {
+    let key = DataKeyToken::PermitKey(owner);
+    e.storage().set(&key, &public_key);
+}
+
+// Checks the payload against the owner's registered key and uses up its nonce
+pub fn use_permit(e: &Env, payload: PermitPayload, signature: BytesN<64>) -> Result<(), Error> {
+    require(
+        e.ledger().sequence() <= payload.expiry,
+        Error::PermitExpired,
+    )?;
+    let nonce = read_nonce(e, payload.owner.clone());
+    require(payload.nonce == nonce, Error::InvalidNonce)?;
+    let public_key = read_permit_key(e, payload.owner.clone()).ok_or(Error::NoPermitKey)?;
+
+    let owner = payload.owner.clone();
+    e.crypto()
+        .ed25519_verify(&public_key, &payload.serialize(e), &signature);
+    write_nonce(e, owner, nonce + 1);
+    Ok(())
+}
@@ -0,0 +1,41 @@
+// Named roles for the privileged entrypoints. The controller is always an admin,
+// and admins hold every role.
+use soroban_sdk::{contracttype, Address, Env};
+
+use super::{metadata::read_controller, storage_types::DataKey};
+use crate::c_errors::{require, Error};
+
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Role {
+    Admin,         // grants and revokes roles, finalizes and manages the pool configuration
+    FeeManager,    // swap fee, fee ramps, fee multipliers and the referral fee cap
+    WeightManager, // rebinds tokens with new weights and balances
+    Pauser,        // turns public swaps on and off
+    TokenBinder,   // binds new tokens before finalize
+    Rescuer,       // sends back tokens the pool holds but doesn't account for
+}
+
+fn read_role(e: &Env, role: Role, account: Address) -> bool {
+    let key = DataKey::Role(role, account);
+    e.storage().get(&key).unwrap_or(Ok(false)).unwrap()
+}
+
+pub fn write_role(e: &Env, role: Role, account: Address, granted: bool) {
+    let key = DataKey::Role(role, account);
+    if granted {
+        e.storage().set(&key, &true);
+    } else {
+        e.storage().remove(&key);
+    }
+}
+
+pub fn has_role(e: &Env, role: Role, account: Address) -> bool {
+    account == read_controller(e)
+        || read_role(e, Role::Admin, account.clone())
+        || read_role(e, role, account)
+}
+
+pub fn require_role(e: &Env, account: &Address, role: Role) -> Result<(), Error> {
+    require(has_role(e, role, account.clone()), Error::MissingRole)
+}
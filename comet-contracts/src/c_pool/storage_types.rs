@@ -1,5 +1,7 @@
 use soroban_sdk::{contracttype, Address, Map, Vec};
 
+use super::roles::Role;
+
 #[contracttype]
 #[derive(Clone, Default, Debug, Eq, PartialEq)]
 pub struct Record {
@@ -9,6 +11,52 @@ pub struct Record {
     pub balance: i128,
 }
 
+// What an address's LP balance is worth, returned by `get_position`
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Position {
+    pub balance: i128, // LP tokens held
+    pub share: i128,   // fraction of the total shares, in BONE
+    pub tokens: Vec<Address>,
+    pub amounts: Vec<i128>, // underlying amount of each token in `tokens`
+    pub value: i128,        // underlying amounts priced in the numeraire token
+}
+
+// Fee accumulator values when an LP's balance last changed, and the fees settled up to then
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FeeCheckpoint {
+    pub fee_per_share: Map<Address, i128>,
+    pub earned: Map<Address, i128>,
+}
+
+// A balance or supply as of the end of a ledger
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Checkpoint {
+    pub ledger: u32,
+    pub value: i128,
+}
+
+// Whose history a checkpoint belongs to, each checkpoint is stored under its own index
+#[derive(Clone)]
+#[contracttype]
+pub enum CheckpointKey {
+    Balance(Address), // LP balance of an address
+    Supply,           // total shares
+    Votes(Address),   // voting power of a delegatee
+}
+
+// Linear move of the swap fee from `start_fee` to `target_fee` over ledger timestamps
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FeeRamp {
+    pub start_fee: i128,
+    pub target_fee: i128,
+    pub start_time: u64,
+    pub end_time: u64,
+}
+
 #[derive(Clone)]
 #[contracttype]
 pub enum DataKey {
@@ -22,6 +70,21 @@ pub enum DataKey {
     TotalShares,
     PublicSwap,
     Finalize,
+    FeeMultiplier(Address), // i128, fraction of the swap fee charged to an address
+    MaxReferralFee,         // i128, highest referral fee a swap may pay out
+    Hooks,                  // Address of the optional hooks contract
+    MinLiquidity,           // i128, LP supply locked in the pool at finalize
+    FeePerShare,            // Map<Address, i128>, swap fees collected per LP token, by token
+    FeeCheckpoint(Address), // FeeCheckpoint, an LP's fee accounting as of its last balance change
+    CheckpointCount(CheckpointKey), // u32, number of checkpoints written for a history
+    Checkpoint(CheckpointKey, u32), // Checkpoint, by index from oldest to newest
+    FeeProposalCount,       // u32, number of swap fee proposals
+    FeeProposal(u32),       // FeeProposal
+    FeeVote(u32, Address),  // bool, set once an LP voted on a proposal
+    SwapFeeRamp,            // FeeRamp, set while the swap fee is ramping
+    SwapFeeVersion,         // u32, bumped on every swap fee change or ramp
+    Role(Role, Address),    // bool, set while the address holds the role
+    LegacyEvents,           // bool, publish swaps, joins and exits in the pre-V1 format
 }
 
 #[derive(Clone)]
@@ -31,12 +94,22 @@ pub struct AllowanceDataKey {
     pub spender: Address,
 }
 
+#[derive(Clone)]
+#[contracttype]
+pub struct AllowanceValue {
+    pub amount: i128,
+    pub expiration_ledger: u32, // last ledger sequence the allowance can be spent in
+}
+
 #[derive(Clone)]
 #[contracttype]
 pub enum DataKeyToken {
-    Allowance(AllowanceDataKey),
+    Allowance(AllowanceDataKey), // i128, allowances written before they could expire
+    ExpiringAllowance(AllowanceDataKey), // AllowanceValue
     Balance(Address),
     Nonce(Address),
+    PermitKey(Address), // BytesN<32>, ed25519 key that signs an owner's permits
+    Delegate(Address),  // Address the holder's votes are delegated to
     State(Address),
     Admin,
     Decimals,
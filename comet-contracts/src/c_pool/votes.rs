@@ -0,0 +1,65 @@
+use soroban_sdk::{Address, Env};
+
+use super::{
+    balance::read_balance,
+    checkpoints::{read_at, read_latest, write_checkpoint},
+    events::{delegate_event, delegate_votes_event},
+    storage_types::{CheckpointKey, DataKeyToken},
+};
+
+// Read who an address delegates its votes to, balances that were never delegated don't vote
+pub fn read_delegate(e: &Env, addr: Address) -> Option<Address> {
+    let key = DataKeyToken::Delegate(addr);
+    e.storage().get(&key).map(|delegatee| delegatee.unwrap())
+}
+
+fn write_delegate(e: &Env, addr: Address, delegatee: Address) {
+    let key = DataKeyToken::Delegate(addr);
+    e.storage().set(&key, &delegatee);
+}
+
+// Current voting power of an address
+pub fn read_votes(e: &Env, addr: Address) -> i128 {
+    read_latest(e, CheckpointKey::Votes(addr))
+}
+
+// Voting power of an address at the end of `ledger`
+pub fn votes_at(e: &Env, addr: Address, ledger: u32) -> i128 {
+    read_at(e, CheckpointKey::Votes(addr), ledger)
+}
+
+// Points `addr`'s votes at `delegatee`, moving its whole balance over
+pub fn delegate(e: &Env, addr: Address, delegatee: Address) {
+    let previous = read_delegate(e, addr.clone());
+    write_delegate(e, addr.clone(), delegatee.clone());
+    delegate_event(e, addr.clone(), previous.clone(), delegatee.clone());
+
+    let balance = read_balance(e, addr);
+    move_votes(e, previous, Some(delegatee), balance);
+}
+
+// Called on every LP balance change, `from` and `to` are the holders whose balances changed
+pub fn move_delegated_votes(e: &Env, from: Option<Address>, to: Option<Address>, amount: i128) {
+    let from_delegate = from.and_then(|addr| read_delegate(e, addr));
+    let to_delegate = to.and_then(|addr| read_delegate(e, addr));
+    move_votes(e, from_delegate, to_delegate, amount);
+}
+
+fn move_votes(e: &Env, from: Option<Address>, to: Option<Address>, amount: i128) {
+    if amount == 0 || from == to {
+        return;
+    }
+    if let Some(from) = from {
+        let previous = read_votes(e, from.clone());
+        write_votes(e, from, previous, previous - amount);
+    }
+    if let Some(to) = to {
+        let previous = read_votes(e, to.clone());
+        write_votes(e, to, previous, previous + amount);
+    }
+}
+
+fn write_votes(e: &Env, delegatee: Address, previous: i128, votes: i128) {
+    write_checkpoint(e, CheckpointKey::Votes(delegatee.clone()), votes);
+    delegate_votes_event(e, delegatee, previous, votes);
+}
@@ -3,7 +3,13 @@
 
 use soroban_sdk::contractimpl;
 mod c_consts;
+mod c_errors;
 mod c_math;
 mod c_num;
 mod c_pool;
 mod tests;
+pub mod testutils;
+
+pub use c_consts::GOV_EXECUTION_DELAY;
+pub use c_errors::Error;
+pub use c_pool::contract::{CometPoolContract, CometPoolContractClient};
@@ -1,11 +1,13 @@
 #![cfg(test)]
 
+use crate::c_errors::Error;
 use crate::c_math::{
-    calc_lp_token_amount_given_token_deposits_in,
+    calc_lp_token_amount_given_exact_tokens_in, calc_lp_token_amount_given_token_deposits_in,
     calc_lp_token_amount_given_token_withdrawal_amount, calc_spot_price,
     calc_token_deposits_in_given_lp_token_amount, calc_token_in_given_token_out,
     calc_token_out_given_token_in, calc_token_withdrawal_amount_given_lp_token_amount,
 };
+use soroban_sdk::{vec, Env};
 
 extern crate std;
 
@@ -33,7 +35,8 @@ fn test_calc_token_out_given_token_in() {
         (0.7 * 1e7) as i128,
         (0.01 * 1e7) as i128,
         (0.001 * 1e7) as i128,
-    );
+    )
+    .unwrap();
 
     std::println!("Val2 = {}", val);
     assert!(val != 0, "result must be non-zero");
@@ -48,7 +51,8 @@ fn test_calc_token_in_given_token_out() {
         (0.7 * 1e7) as i128,
         (0.001 * 1e7) as i128,
         (0.0001 * 1e7) as i128,
-    );
+    )
+    .unwrap();
 
     std::println!("Val3 = {}", val);
     assert!(val != 0, "result must be non-zero");
@@ -63,7 +67,8 @@ fn test_calc_lp_token_amount_given_token_deposits_in() {
         (0.7 * 1e7) as i128,
         (0.001 * 1e7) as i128,
         (0.0001 * 1e7) as i128,
-    );
+    )
+    .unwrap();
     std::println!("Val4 = {}", val);
     assert!(val != 0, "result must be non-zero");
 }
@@ -77,7 +82,8 @@ fn test_calc_token_deposits_in_given_lp_token_amount() {
         (0.7 * 1e7) as i128,
         (0.001 * 1e7) as i128,
         (0.0001 * 1e7) as i128,
-    );
+    )
+    .unwrap();
     std::println!("Val5 = {}", val);
     assert!(val != 0, "result must be non-zero");
 }
@@ -91,7 +97,8 @@ fn test_calc_lp_token_amount_given_token_withdrawal_amount() {
         (0.7 * 1e7) as i128,
         (0.001 * 1e7) as i128,
         (0.0001 * 1e7) as i128,
-    );
+    )
+    .unwrap();
     std::println!("Val6 = {}", val);
     assert!(val != 0, "result must be non-zero");
 }
@@ -105,7 +112,49 @@ fn test_calc_token_withdrawal_amount_given_lp_token_amount() {
         (0.7 * 1e7) as i128,
         (0.001 * 1e7) as i128,
         (0.0001 * 1e7) as i128,
-    );
+    )
+    .unwrap();
     std::println!("Val7 = {}", val);
     assert!(val != 0, "result must be non-zero");
 }
+
+#[test]
+fn test_calc_lp_token_amount_given_exact_tokens_in() {
+    let env = Env::default();
+    let val = calc_lp_token_amount_given_exact_tokens_in(
+        &vec![&env, 1 * 1e7 as i128, 2 * 1e7 as i128],
+        &vec![&env, (0.2 * 1e7) as i128, (0.5 * 1e7) as i128],
+        &vec![&env, (0.01 * 1e7) as i128, 0],
+        20 * 1e7 as i128,
+        (0.7 * 1e7) as i128,
+        (0.001 * 1e7) as i128,
+    )
+    .unwrap();
+    std::println!("Val8 = {}", val);
+    assert!(val != 0, "result must be non-zero");
+}
+
+#[test]
+fn test_calc_pow_base_out_of_range() {
+    // Minting as many LP tokens as exist doubles the pool, past the largest base c_pow takes
+    let val = calc_token_deposits_in_given_lp_token_amount(
+        1 * 1e7 as i128,
+        (0.2 * 1e7) as i128,
+        20 * 1e7 as i128,
+        (0.7 * 1e7) as i128,
+        20 * 1e7 as i128,
+        (0.0001 * 1e7) as i128,
+    );
+    assert_eq!(val, Err(Error::CPowBaseTooHigh));
+
+    // Burning the whole supply leaves a zero base
+    let val = calc_token_withdrawal_amount_given_lp_token_amount(
+        1 * 1e7 as i128,
+        (0.2 * 1e7) as i128,
+        20 * 1e7 as i128,
+        (0.7 * 1e7) as i128,
+        20 * 1e7 as i128,
+        (0.0001 * 1e7) as i128,
+    );
+    assert_eq!(val, Err(Error::CPowBaseTooLow));
+}
@@ -1,6 +1,7 @@
 // #![cfg(test)]
 
 use crate::c_consts;
+use crate::c_errors::Error;
 use crate::c_num::c_pow_approx;
 use crate::c_num::c_powi;
 use crate::c_num::{c_add, c_div, c_mul, c_pow, c_sub};
@@ -10,7 +11,7 @@ use crate::c_consts::BONE;
 #[test]
 // tests that an error is returned when adding a positive number to i128::MAX.
 fn test_c_add_overflow() {
-    assert_eq!(c_add(1, i128::MAX).err().unwrap(), "ERR_ADD_OVERFLOW");
+    assert_eq!(c_add(1, i128::MAX).err().unwrap(), Error::AddOverflow);
 }
 
 #[test]
@@ -18,28 +19,28 @@ fn test_c_add_overflow() {
 fn test_c_sub_underflow() {
     match c_sub(1, 2) {
         Ok(result) => assert_eq!(result, -1),
-        Err(err_msg) => panic!("Expected Ok(-1), but got Err({})", err_msg),
+        Err(err_msg) => panic!("Expected Ok(-1), but got Err({:?})", err_msg),
     }
 }
 
 #[test]
 // tests that an error is returned when multiplying a number by i128::MAX.
 fn test_c_mul_overflow() {
-    assert_eq!(c_mul(2, i128::MAX).err().unwrap(), "ERR_MUL_OVERFLOW");
+    assert_eq!(c_mul(2, i128::MAX).err().unwrap(), Error::MulOverflow);
 }
 
 #[test]
 // tests that an error is returned when dividing a number by zero.
 fn test_c_div_error_on_div_by_zero() {
-    assert_eq!(c_div(1, 0).err().unwrap(), "ERR_DIV_ZERO");
+    assert_eq!(c_div(1, 0).err().unwrap(), Error::DivZero);
 }
 
 #[test]
 // tests that an error is returned when calculating the power of a number
 // that is too low or too high.
 fn test_c_pow() {
-    assert_eq!(c_pow(0, 2).err().unwrap(), "ERR_CPOW_BASE_TOO_LOW");
-    assert_eq!(c_pow(i128::MAX, 2).err().unwrap(), "ERR_CPOW_BASE_TOO_HIGH");
+    assert_eq!(c_pow(0, 2).err().unwrap(), Error::CPowBaseTooLow);
+    assert_eq!(c_pow(i128::MAX, 2).err().unwrap(), Error::CPowBaseTooHigh);
 }
 
 #[test]
@@ -84,7 +85,7 @@ fn test_c_pow_approx() {
     let exp = 2;
     let precision = 1;
     let bone = c_consts::BONE;
-    let result = c_pow_approx(base * bone, exp * bone, precision);
+    let result = c_pow_approx(base * bone, exp * bone, precision).unwrap();
     assert_eq!(result, 4 * bone);
 }
 
@@ -93,7 +94,7 @@ fn test_c_pow_approx() {
 fn test_c_powi() {
     let base = 2 * c_consts::BONE;
     let exp = 3;
-    let result = c_powi(base, exp);
+    let result = c_powi(base, exp).unwrap();
     assert_eq!(
         result,
         (2i128.pow(exp as u32) * c_consts::BONE.pow(exp as u32))
@@ -110,7 +111,7 @@ fn test_c_sub_max_min() {
     let min = i128::MIN + 1;
     match c_sub(max, min - 1) {
         Ok(result) => panic!("Expected an error, but got Ok({})", result),
-        Err(err_msg) => assert_eq!(err_msg, "ERR_SUB_OVERFLOW"),
+        Err(err_msg) => assert_eq!(err_msg, Error::SubOverflow),
     }
 }
 
@@ -119,7 +120,7 @@ fn test_c_sub_max_min() {
 fn test_c_mul_large() {
     let a = i128::MAX / 2 + 1;
     let b = 2;
-    assert_eq!(c_mul(a, b).err().unwrap(), "ERR_MUL_OVERFLOW");
+    assert_eq!(c_mul(a, b).err().unwrap(), Error::MulOverflow);
 }
 
 #[test]
@@ -127,7 +128,7 @@ fn test_c_mul_large() {
 fn test_c_div_large() {
     let a = i128::MAX;
     let b = 2;
-    assert_eq!(c_div(a, b).err().unwrap(), "ERR_DIV_INTERNAL");
+    assert_eq!(c_div(a, b).err().unwrap(), Error::DivInternal);
 }
 
 #[test]
@@ -138,7 +139,7 @@ fn test_c_pow_approx_large() {
     let exp = 3;
     let precision = 1;
     let bone = c_consts::BONE;
-    let result = c_pow_approx(base * bone, exp * bone, precision);
+    let result = c_pow_approx(base * bone, exp * bone, precision).unwrap();
     assert_eq!(result, 1000 * bone);
 }
 
@@ -148,7 +149,7 @@ fn test_c_pow_approx_large() {
 fn test_c_powi_even_exponent() {
     let base = 3 * c_consts::BONE;
     let exp = 4;
-    let result = c_powi(base, exp);
+    let result = c_powi(base, exp).unwrap();
     assert_eq!(
         result,
         (3i128.pow(exp as u32) * c_consts::BONE.pow(exp as u32))
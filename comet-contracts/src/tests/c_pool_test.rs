@@ -5,21 +5,27 @@
 
 use std::println;
 extern crate std;
-use crate::c_consts::BONE;
+use crate::c_consts::{BONE, GOV_EXECUTION_DELAY, GOV_EXECUTION_WINDOW, MAX_FEE, MIN_LIQUIDITY};
+use crate::c_errors::Error;
+use crate::c_math::{calc_lp_token_amount_given_token_deposits_in, calc_token_out_given_token_in};
 use crate::c_pool::contract::CometPoolContract;
 use crate::c_pool::contract::CometPoolContractClient;
+use crate::c_pool::events::{
+    JoinEvent, LegacyEventsEvent, LegacyJoinEvent, LegacySwapEvent, MaxReferralFeeEvent,
+    MinLiquidityEvent, RebindEvent, SnapshotEvent, SwapFeeEvent,
+};
+use crate::c_pool::hooks::HookDecision;
+use crate::c_pool::permit::PermitPayload;
+use crate::c_pool::roles::Role;
+use crate::c_pool::storage_types::{AllowanceDataKey, DataKeyToken, FeeRamp};
+use crate::testutils::{create_bound_pool, create_token_contract, token};
+use soroban_sdk::serde::Serialize;
+use soroban_sdk::testutils::{Events, Ledger};
 use soroban_sdk::xdr::AccountId;
 // use soroban_sdk::Address;
-use soroban_sdk::{vec, BytesN, Env, Symbol};
+use soroban_sdk::{contractimpl, vec, BytesN, Env, Symbol, Vec};
 
-use soroban_sdk::{testutils::Address as _, Address, IntoVal};
-mod token {
-    soroban_sdk::contractimport!(file = "../soroban_token_spec.wasm");
-}
-
-fn create_token_contract(e: &Env, admin: &soroban_sdk::Address) -> token::Client {
-    token::Client::new(&e, &e.register_stellar_asset_contract(admin.clone()))
-}
+use soroban_sdk::{testutils::Address as _, Address, IntoVal, RawVal, TryFromVal};
 
 fn install_token_wasm(e: &Env) -> BytesN<32> {
     soroban_sdk::contractimport!(
@@ -42,7 +48,7 @@ fn test_pool_functions() {
     let client = CometPoolContractClient::new(&env, &contract_id);
     let factory = admin.clone();
     let controller_arg = factory.clone();
-    client.init(&factory, &controller_arg, &install_token_wasm(&env));
+    client.init(&factory, &controller_arg, &install_token_wasm(&env), &None);
 
     // Create Admin
     let mut admin1 = soroban_sdk::Address::random(&env);
@@ -106,10 +112,11 @@ fn test_pool_functions() {
     client.set_swap_fee(&to_stroop(0.003), &controller);
     let swap_fee = client.get_swap_fee();
     assert_eq!(swap_fee, to_stroop(0.003));
-    client.finalize();
+    client.finalize(&controller);
     let contract_share: [u8; 32] = client.share_id().into();
     let token_share = token::Client::new(&env, &contract_share);
-    assert_eq!(token_share.balance(&controller), 100 * BONE);
+    assert_eq!(token_share.balance(&controller), 100 * BONE - MIN_LIQUIDITY);
+    assert_eq!(token_share.balance(&contract_address), MIN_LIQUIDITY);
 
     token1.incr_allow(&user1, &contract_address, &i128::MAX);
     token2.incr_allow(&user1, &contract_address, &i128::MAX);
@@ -145,6 +152,8 @@ fn test_pool_functions() {
         &to_stroop(475),
         &to_stroop(200),
         &user2,
+        &None,
+        &0,
     );
 
     let val = client.get_spot_price(&token3.address(), &token1.address());
@@ -158,7 +167,1434 @@ fn test_pool_functions() {
         &to_stroop(1.0),
         &to_stroop(500),
         &user2,
+        &None,
+        &0,
     );
     let val = client.get_spot_price(&token2.address(), &token1.address());
     println!("{}", val);
-}
\ No newline at end of file
+}
+
+// Creates a finalized pool with three bound tokens and a 0.3% swap fee.
+// The returned admin controls both the pool and the underlying tokens.
+fn setup_pool(
+    env: &Env,
+) -> (
+    CometPoolContractClient,
+    Address,
+    std::vec::Vec<token::Client>,
+) {
+    setup_pool_with_hooks(env, None)
+}
+
+fn setup_pool_with_hooks(
+    env: &Env,
+    hooks: Option<Address>,
+) -> (
+    CometPoolContractClient,
+    Address,
+    std::vec::Vec<token::Client>,
+) {
+    let (client, admin, tokens) = setup_bound_pool(env, hooks);
+    client.finalize(&admin);
+    (client, admin, tokens)
+}
+
+// Same pool as `setup_pool_with_hooks`, left unfinalized
+fn setup_bound_pool(
+    env: &Env,
+    hooks: Option<Address>,
+) -> (
+    CometPoolContractClient,
+    Address,
+    std::vec::Vec<token::Client>,
+) {
+    let admin = soroban_sdk::Address::random(env);
+    let (client, tokens) = create_bound_pool(env, &admin, hooks);
+    (client, admin, tokens)
+}
+
+#[test]
+fn test_fee_multiplier() {
+    let env = Env::default();
+    let (client, admin, tokens) = setup_pool(&env);
+    let contract_address = Address::from_contract_id(&env, &client.contract_id);
+    let user = soroban_sdk::Address::random(&env);
+    let other = soroban_sdk::Address::random(&env);
+
+    client.set_fee_multiplier(&admin, &user, &(BONE / 2));
+    assert_eq!(client.get_effective_fee(&user), to_stroop(0.0015));
+    assert_eq!(client.get_effective_fee(&other), to_stroop(0.003));
+
+    let token_in = &tokens[0];
+    let token_out = &tokens[2];
+    token_in.mint(&admin, &user, &to_stroop(5));
+    token_in.incr_allow(&user, &contract_address, &i128::MAX);
+
+    let expected_out = calc_token_out_given_token_in(
+        client.get_balance(&token_in.address()),
+        client.get_denormalized_weight(&token_in.address()),
+        client.get_balance(&token_out.address()),
+        client.get_denormalized_weight(&token_out.address()),
+        to_stroop(2.5),
+        to_stroop(0.0015),
+    )
+    .unwrap();
+    let (token_amount_out, _) = client.swap_exact_amount_in(
+        &token_in.address(),
+        &to_stroop(2.5),
+        &token_out.address(),
+        &0,
+        &i128::MAX,
+        &user,
+        &None,
+        &0,
+    );
+    assert_eq!(token_amount_out, expected_out);
+    assert_eq!(token_out.balance(&user), expected_out);
+}
+
+#[test]
+fn test_swap_referral_fee() {
+    let env = Env::default();
+    let (client, admin, tokens) = setup_pool(&env);
+    let contract_address = Address::from_contract_id(&env, &client.contract_id);
+    let user = soroban_sdk::Address::random(&env);
+    let referrer = soroban_sdk::Address::random(&env);
+
+    let token_in = &tokens[0];
+    let token_out = &tokens[2];
+    token_in.mint(&admin, &user, &to_stroop(5));
+    token_in.incr_allow(&user, &contract_address, &i128::MAX);
+
+    // Referrals are disabled until the controller sets a maximum
+    assert_eq!(client.get_max_referral_fee(), 0);
+    client.set_max_referral_fee(&admin, &to_stroop(0.01));
+
+    let balance_in_before = client.get_balance(&token_in.address());
+    client.swap_exact_amount_in(
+        &token_in.address(),
+        &to_stroop(2),
+        &token_out.address(),
+        &0,
+        &i128::MAX,
+        &user,
+        &Some(referrer.clone()),
+        &to_stroop(0.01),
+    );
+
+    assert_eq!(token_in.balance(&referrer), to_stroop(0.02));
+    assert_eq!(token_in.balance(&user), to_stroop(3));
+    assert_eq!(
+        client.get_balance(&token_in.address()),
+        balance_in_before + to_stroop(1.98)
+    );
+}
+
+// Hooks that charge the maximum fee on swaps and veto any swap above one token
+pub struct CappedSwapHooks;
+
+#[contractimpl]
+impl CappedSwapHooks {
+    #[allow(clippy::too_many_arguments)]
+    pub fn before_swap(
+        e: Env,
+        pool: Address,
+        user: Address,
+        token_in: Address,
+        token_out: Address,
+        token_amount: i128,
+        exact_in: bool,
+        fee: i128,
+    ) -> HookDecision {
+        HookDecision {
+            allow: token_amount <= to_stroop(1),
+            fee: MAX_FEE,
+        }
+    }
+
+    pub fn after_swap(
+        e: Env,
+        pool: Address,
+        user: Address,
+        token_in: Address,
+        token_out: Address,
+        token_amount_in: i128,
+        token_amount_out: i128,
+    ) {
+    }
+
+    pub fn before_join(
+        e: Env,
+        pool: Address,
+        user: Address,
+        tokens_in: Vec<Address>,
+        pool_amount: i128,
+        fee: i128,
+    ) -> HookDecision {
+        HookDecision { allow: true, fee }
+    }
+
+    pub fn after_join(
+        e: Env,
+        pool: Address,
+        user: Address,
+        tokens_in: Vec<Address>,
+        token_amounts_in: Vec<i128>,
+        pool_amount_out: i128,
+    ) {
+    }
+
+    pub fn before_exit(
+        e: Env,
+        pool: Address,
+        user: Address,
+        tokens_out: Vec<Address>,
+        pool_amount: i128,
+        fee: i128,
+    ) -> HookDecision {
+        HookDecision { allow: true, fee }
+    }
+
+    pub fn after_exit(
+        e: Env,
+        pool: Address,
+        user: Address,
+        tokens_out: Vec<Address>,
+        token_amounts_out: Vec<i128>,
+        pool_amount_in: i128,
+    ) {
+    }
+}
+
+fn setup_capped_swap_pool(
+    env: &Env,
+) -> (
+    CometPoolContractClient,
+    Address,
+    std::vec::Vec<token::Client>,
+) {
+    let hooks_id = env.register_contract(None, CappedSwapHooks);
+    let hooks = Address::from_contract_id(env, &hooks_id);
+    let (client, admin, tokens) = setup_pool_with_hooks(env, Some(hooks.clone()));
+    assert_eq!(client.get_hooks(), Some(hooks));
+
+    let contract_address = Address::from_contract_id(env, &client.contract_id);
+    tokens[0].incr_allow(&admin, &contract_address, &i128::MAX);
+    tokens[0].mint(&admin, &admin, &to_stroop(5));
+    (client, admin, tokens)
+}
+
+#[test]
+fn test_hooks_adjust_swap_fee() {
+    let env = Env::default();
+    let (client, admin, tokens) = setup_capped_swap_pool(&env);
+    let token_in = &tokens[0];
+    let token_out = &tokens[2];
+
+    let expected_out = calc_token_out_given_token_in(
+        client.get_balance(&token_in.address()),
+        client.get_denormalized_weight(&token_in.address()),
+        client.get_balance(&token_out.address()),
+        client.get_denormalized_weight(&token_out.address()),
+        to_stroop(0.5),
+        MAX_FEE,
+    )
+    .unwrap();
+    let (token_amount_out, _) = client.swap_exact_amount_in(
+        &token_in.address(),
+        &to_stroop(0.5),
+        &token_out.address(),
+        &0,
+        &i128::MAX,
+        &admin,
+        &None,
+        &0,
+    );
+    assert_eq!(token_amount_out, expected_out);
+}
+
+#[test]
+fn test_hooks_veto_swap() {
+    let env = Env::default();
+    let (client, admin, tokens) = setup_capped_swap_pool(&env);
+
+    let result = client.try_swap_exact_amount_in(
+        &tokens[0].address(),
+        &to_stroop(2),
+        &tokens[2].address(),
+        &0,
+        &i128::MAX,
+        &admin,
+        &None,
+        &0,
+    );
+    assert_eq!(result, Err(Ok(Error::HookVeto)));
+}
+
+#[test]
+fn test_join_exact_tokens_in() {
+    let env = Env::default();
+    let (client, admin, tokens) = setup_pool(&env);
+    let contract_address = Address::from_contract_id(&env, &client.contract_id);
+    let user = soroban_sdk::Address::random(&env);
+    let contract_share: [u8; 32] = client.share_id().into();
+    let token_share = token::Client::new(&env, &contract_share);
+
+    // 10% of every pool balance
+    let amounts = [to_stroop(5), to_stroop(2), to_stroop(1000)];
+    for (token, amount) in tokens.iter().zip(amounts) {
+        token.mint(&admin, &user, &amount);
+        token.incr_allow(&user, &contract_address, &i128::MAX);
+    }
+    let amounts_in = vec![&env, amounts[0], amounts[1], amounts[2]];
+
+    // A proportional deposit pays no fee and grows the supply by 10%
+    let quote = client.quote_join_exact_tokens_in(&amounts_in);
+    assert!(quote <= 10 * BONE);
+    assert!(quote > to_stroop(9.999));
+
+    let pool_amount_out = client.join_exact_tokens_in(&amounts_in, &quote, &user);
+    assert_eq!(pool_amount_out, quote);
+    assert_eq!(token_share.balance(&user), pool_amount_out);
+    assert_eq!(client.get_balance(&tokens[2].address()), to_stroop(11000));
+    assert_eq!(tokens[2].balance(&user), 0);
+
+    // A single token basket is charged like a single-sided deposit
+    let single_quote = client.quote_join_exact_tokens_in(&vec![&env, to_stroop(5), 0, 0]);
+    let single_sided = calc_lp_token_amount_given_token_deposits_in(
+        client.get_balance(&tokens[0].address()),
+        client.get_denormalized_weight(&tokens[0].address()),
+        100 * BONE + pool_amount_out,
+        client.get_total_denormalized_weight(),
+        to_stroop(5),
+        client.get_swap_fee(),
+    )
+    .unwrap();
+    assert!((single_quote - single_sided).abs() <= 10);
+}
+
+// Funds `user` with `amounts` of every pool token and approves the pool to pull them
+fn fund_user(
+    env: &Env,
+    client: &CometPoolContractClient,
+    admin: &Address,
+    tokens: &[token::Client],
+    user: &Address,
+    amounts: &[i128],
+) {
+    let contract_address = Address::from_contract_id(env, &client.contract_id);
+    for (token, amount) in tokens.iter().zip(amounts) {
+        token.mint(admin, user, amount);
+        token.incr_allow(user, &contract_address, &i128::MAX);
+    }
+}
+
+#[test]
+fn test_exit_exact_tokens_out_proportional_round_trip() {
+    let env = Env::default();
+    let (client, admin, tokens) = setup_pool(&env);
+    let user = soroban_sdk::Address::random(&env);
+    let amounts = [to_stroop(5), to_stroop(2), to_stroop(1000)];
+    fund_user(&env, &client, &admin, &tokens, &user, &amounts);
+    let basket = vec![&env, amounts[0], amounts[1], amounts[2]];
+
+    let pool_amount_out = client.join_exact_tokens_in(&basket, &0, &user);
+    let quote = client.quote_exit_exact_tokens_out(&basket);
+    let pool_amount_in = client.exit_exact_tokens_out(&basket, &pool_amount_out, &user);
+    assert_eq!(pool_amount_in, quote);
+
+    // Nothing is charged on a proportional round trip, apart from rounding
+    assert!(pool_amount_in <= pool_amount_out);
+    assert!(pool_amount_out - pool_amount_in <= 10);
+    for (token, amount) in tokens.iter().zip(amounts) {
+        assert_eq!(token.balance(&user), amount);
+    }
+    assert_eq!(client.get_balance(&tokens[2].address()), to_stroop(10000));
+}
+
+#[test]
+fn test_exit_exact_tokens_out_uneven_round_trip() {
+    let env = Env::default();
+    let (client, admin, tokens) = setup_pool(&env);
+    let contract_share: [u8; 32] = client.share_id().into();
+    let token_share = token::Client::new(&env, &contract_share);
+    let user = soroban_sdk::Address::random(&env);
+    let amounts = [to_stroop(5), 0, to_stroop(300)];
+    fund_user(&env, &client, &admin, &tokens, &user, &amounts);
+    let basket = vec![&env, amounts[0], amounts[1], amounts[2]];
+
+    let pool_amount_out = client.join_exact_tokens_in(&basket, &0, &user);
+
+    // Getting the same uneven basket back costs more shares than it minted
+    let pool_amount_in = client.quote_exit_exact_tokens_out(&basket);
+    assert!(pool_amount_in > pool_amount_out);
+
+    // Taking out a bit less fits in the minted shares
+    let smaller_basket = vec![&env, to_stroop(4.9), 0, to_stroop(294)];
+    let pool_amount_in = client.exit_exact_tokens_out(&smaller_basket, &pool_amount_out, &user);
+    assert_eq!(token_share.balance(&user), pool_amount_out - pool_amount_in);
+    assert_eq!(tokens[0].balance(&user), to_stroop(4.9));
+    assert_eq!(tokens[2].balance(&user), to_stroop(294));
+}
+
+#[test]
+fn test_dep_lp_tokn_amt_out_pow_base_too_high() {
+    let env = Env::default();
+    let (client, admin, tokens) = setup_pool(&env);
+
+    // Asking for as many LP tokens as exist puts the pool ratio at 2, outside c_pow's range
+    let result = client.try_dep_lp_tokn_amt_out_get_tokn_in(
+        &tokens[0].address(),
+        &(100 * BONE),
+        &i128::MAX,
+        &admin,
+    );
+    assert_eq!(result, Err(Ok(Error::CPowBaseTooHigh)));
+    assert_eq!(client.get_balance(&tokens[0].address()), to_stroop(50));
+}
+
+#[test]
+fn test_wdr_tokn_amt_in_pow_base_too_low() {
+    let env = Env::default();
+    let (client, admin, tokens) = setup_pool(&env);
+
+    let result = client.try_wdr_tokn_amt_in_get_lp_tokns_out(
+        &tokens[0].address(),
+        &(100 * BONE),
+        &0,
+        &admin,
+    );
+    assert_eq!(result, Err(Ok(Error::CPowBaseTooLow)));
+}
+
+#[test]
+fn test_exit_exact_tokens_out_max_out_ratio() {
+    let env = Env::default();
+    let (client, admin, tokens) = setup_pool(&env);
+
+    // More than a third of the first token's balance
+    let result =
+        client.try_exit_exact_tokens_out(&vec![&env, to_stroop(20), 0, 0], &i128::MAX, &admin);
+    assert_eq!(result, Err(Ok(Error::MaxOutRatio)));
+}
+
+#[test]
+fn test_swap_exact_amount_out_max_out_ratio() {
+    let env = Env::default();
+    let (client, admin, tokens) = setup_pool(&env);
+
+    let result = client.try_swap_exact_amount_out(
+        &tokens[1].address(),
+        &i128::MAX,
+        &tokens[0].address(),
+        &to_stroop(20),
+        &i128::MAX,
+        &admin,
+        &None,
+        &0,
+    );
+    assert_eq!(result, Err(Ok(Error::MaxOutRatio)));
+}
+
+#[test]
+fn test_unbound_token_and_negative_amount_errors() {
+    let env = Env::default();
+    let (client, admin, tokens) = setup_pool(&env);
+    let unbound = create_token_contract(&env, &admin);
+
+    assert!(!client.is_bound(&unbound.address()));
+    assert_eq!(
+        client.try_get_balance(&unbound.address()),
+        Err(Ok(Error::NotBound))
+    );
+    assert_eq!(
+        client.try_get_spot_price(&tokens[0].address(), &unbound.address()),
+        Err(Ok(Error::NotBound))
+    );
+    assert_eq!(
+        client.try_get_spot_price_sans_fee(&unbound.address(), &tokens[0].address()),
+        Err(Ok(Error::NotBound))
+    );
+    assert_eq!(
+        client.try_zap_in(&tokens[0].address(), &-1, &0, &admin),
+        Err(Ok(Error::NegativeAmount))
+    );
+    assert_eq!(
+        client.try_quote_join_exact_tokens_in(&vec![&env, -1, 0, 0]),
+        Err(Ok(Error::NegativeAmount))
+    );
+    assert_eq!(
+        client.try_xfer(&admin, &admin, &-1),
+        Err(Ok(Error::NegativeAmount))
+    );
+}
+
+#[test]
+fn test_join_pool_max() {
+    let env = Env::default();
+    let (client, admin, tokens) = setup_pool(&env);
+    let contract_share: [u8; 32] = client.share_id().into();
+    let token_share = token::Client::new(&env, &contract_share);
+    let user = soroban_sdk::Address::random(&env);
+    let budget = [to_stroop(5), to_stroop(1), to_stroop(1000)];
+    fund_user(&env, &client, &admin, &tokens, &user, &budget);
+
+    // The second token only covers 5% of its pool balance, which caps the join
+    let (pool_amount_out, amounts_in) =
+        client.join_pool_max(&vec![&env, budget[0], budget[1], budget[2]], &0, &user);
+    assert_eq!(pool_amount_out, to_stroop(5));
+    assert_eq!(
+        amounts_in,
+        vec![&env, to_stroop(2.5), to_stroop(1), to_stroop(500)]
+    );
+    assert_eq!(token_share.balance(&user), to_stroop(5));
+    assert_eq!(tokens[0].balance(&user), to_stroop(2.5));
+    assert_eq!(tokens[1].balance(&user), 0);
+}
+
+#[test]
+fn test_zap_in() {
+    let env = Env::default();
+    let (client, admin, tokens) = setup_pool(&env);
+    let contract_share: [u8; 32] = client.share_id().into();
+    let token_share = token::Client::new(&env, &contract_share);
+    let user = soroban_sdk::Address::random(&env);
+    fund_user(&env, &client, &admin, &tokens, &user, &[to_stroop(3), 0, 0]);
+
+    // The quote uses the user's discounted fee, like the zap itself
+    let full_fee_quote = client.quote_zap_in(&tokens[0].address(), &to_stroop(3), &admin);
+    client.set_fee_multiplier(&admin, &user, &(BONE / 2));
+    let quote = client.quote_zap_in(&tokens[0].address(), &to_stroop(3), &user);
+    assert!(quote > full_fee_quote);
+    let pool_amount_out = client.zap_in(&tokens[0].address(), &to_stroop(3), &quote, &user);
+    assert_eq!(pool_amount_out, quote);
+    assert_eq!(token_share.balance(&user), pool_amount_out);
+
+    // 3 of the first token is worth 2% of the pool, almost all of it ends up as LP tokens
+    // and the dust left over from the swaps is returned
+    assert!(pool_amount_out > to_stroop(1.9));
+    assert!(pool_amount_out < to_stroop(2));
+    assert!(tokens[0].balance(&user) < to_stroop(0.01));
+    assert!(tokens[1].balance(&user) > 0);
+
+    // The pool's records still match what it holds
+    let contract_address = Address::from_contract_id(&env, &client.contract_id);
+    for token in tokens.iter() {
+        assert_eq!(
+            client.get_balance(&token.address()),
+            token.balance(&contract_address)
+        );
+    }
+}
+
+#[test]
+fn test_min_liquidity_stays_locked() {
+    let env = Env::default();
+    let (client, admin, tokens) = setup_pool(&env);
+    let contract_share: [u8; 32] = client.share_id().into();
+    let token_share = token::Client::new(&env, &contract_share);
+    let contract_address = Address::from_contract_id(&env, &client.contract_id);
+    assert_eq!(client.get_min_liquidity(), MIN_LIQUIDITY);
+    assert_eq!(token_share.balance(&contract_address), MIN_LIQUIDITY);
+
+    // The controller can exit everything it holds, the locked supply keeps the pool priced
+    let controller_shares = token_share.balance(&admin);
+    client.exit_pool(&controller_shares, &vec![&env, 0, 0, 0], &admin);
+    assert_eq!(token_share.balance(&admin), 0);
+    assert_eq!(token_share.balance(&contract_address), MIN_LIQUIDITY);
+    assert!(client.get_balance(&tokens[2].address()) > 0);
+}
+
+#[test]
+fn test_min_liquidity_bounds() {
+    let env = Env::default();
+    let admin = soroban_sdk::Address::random(&env);
+    let contract_id = env.register_contract(None, CometPoolContract);
+    let client = CometPoolContractClient::new(&env, &contract_id);
+    client.init(&admin, &admin, &install_token_wasm(&env), &None);
+
+    let result = client.try_set_min_liquidity(&admin, &(100 * BONE));
+    assert_eq!(result, Err(Ok(Error::MaxMinLiquidity)));
+}
+
+#[test]
+fn test_finalize_with_supply_and_recipient() {
+    let env = Env::default();
+    let (client, admin, _) = setup_bound_pool(&env, None);
+    let treasury = soroban_sdk::Address::random(&env);
+    let contract_share: [u8; 32] = client.share_id().into();
+    let token_share = token::Client::new(&env, &contract_share);
+    let contract_address = Address::from_contract_id(&env, &client.contract_id);
+
+    client.finalize_with(&to_stroop(1000), &treasury, &admin);
+    assert_eq!(
+        token_share.balance(&treasury),
+        to_stroop(1000) - MIN_LIQUIDITY
+    );
+    assert_eq!(token_share.balance(&contract_address), MIN_LIQUIDITY);
+    assert_eq!(token_share.balance(&admin), 0);
+}
+
+#[test]
+fn test_finalize_with_supply_too_low() {
+    let env = Env::default();
+    let (client, admin, _) = setup_bound_pool(&env, None);
+    let result = client.try_finalize_with(&(BONE / 2), &admin, &admin);
+    assert_eq!(result, Err(Ok(Error::MinInitSupply)));
+}
+
+#[test]
+fn test_config_events() {
+    let env = Env::default();
+    let (client, admin, tokens) = setup_bound_pool(&env, None);
+
+    client.rebind(&tokens[0].address(), &to_stroop(40), &to_stroop(10), &admin);
+    let (topics, data) = last_pool_event(&env, &client, Symbol::short("LOG"));
+    let expected: Vec<RawVal> = (Symbol::short("LOG"), Symbol::short("REBIND")).into_val(&env);
+    assert_eq!(topics, expected);
+    assert_eq!(
+        RebindEvent::try_from_val(&env, &data).unwrap(),
+        RebindEvent {
+            caller: admin.clone(),
+            token: tokens[0].address(),
+            old_balance: to_stroop(50),
+            new_balance: to_stroop(40),
+            old_denorm: to_stroop(5),
+            new_denorm: to_stroop(10),
+        }
+    );
+
+    client.set_swap_fee(&to_stroop(0.002), &admin);
+    let (_, topics, data) = env.events().all().last().unwrap().unwrap();
+    let expected: Vec<RawVal> = (Symbol::short("LOG"), Symbol::short("SWAP_FEE")).into_val(&env);
+    assert_eq!(topics, expected);
+    assert_eq!(
+        SwapFeeEvent::try_from_val(&env, &data).unwrap(),
+        SwapFeeEvent {
+            caller: admin.clone(),
+            old_fee: to_stroop(0.003),
+            new_fee: to_stroop(0.002),
+        }
+    );
+
+    client.set_min_liquidity(&admin, &(MIN_LIQUIDITY * 2));
+    let (topics, data) = last_pool_event(&env, &client, Symbol::short("LOG"));
+    let expected: Vec<RawVal> =
+        (Symbol::short("LOG"), Symbol::new(&env, "MIN_LIQUIDITY")).into_val(&env);
+    assert_eq!(topics, expected);
+    assert_eq!(
+        MinLiquidityEvent::try_from_val(&env, &data).unwrap(),
+        MinLiquidityEvent {
+            caller: admin.clone(),
+            old_amount: MIN_LIQUIDITY,
+            new_amount: MIN_LIQUIDITY * 2,
+        }
+    );
+
+    client.set_max_referral_fee(&admin, &to_stroop(0.001));
+    let (topics, data) = last_pool_event(&env, &client, Symbol::short("LOG"));
+    let expected: Vec<RawVal> =
+        (Symbol::short("LOG"), Symbol::new(&env, "MAX_REFERRAL_FEE")).into_val(&env);
+    assert_eq!(topics, expected);
+    assert_eq!(
+        MaxReferralFeeEvent::try_from_val(&env, &data)
+            .unwrap()
+            .new_fee,
+        to_stroop(0.001)
+    );
+
+    client.set_legacy_events(&admin, &true);
+    let (topics, data) = last_pool_event(&env, &client, Symbol::short("LOG"));
+    let expected: Vec<RawVal> =
+        (Symbol::short("LOG"), Symbol::new(&env, "LEGACY_EVENTS")).into_val(&env);
+    assert_eq!(topics, expected);
+    assert_eq!(
+        LegacyEventsEvent::try_from_val(&env, &data).unwrap(),
+        LegacyEventsEvent {
+            caller: admin,
+            old_value: false,
+            new_value: true,
+        }
+    );
+}
+
+// Topics and data of the last event the pool published with `name` as its first topic
+fn last_pool_event(
+    env: &Env,
+    client: &CometPoolContractClient,
+    name: Symbol,
+) -> (Vec<RawVal>, RawVal) {
+    let mut found = None;
+    for event in env.events().all().iter() {
+        let (contract_id, topics, data) = event.unwrap();
+        let first = Symbol::try_from_val(env, &topics.get(0).unwrap().unwrap());
+        if contract_id == client.contract_id && matches!(first, Ok(s) if s == name) {
+            found = Some((topics, data));
+        }
+    }
+    found.unwrap()
+}
+
+#[test]
+fn test_swap_join_exit_event_topics() {
+    let env = Env::default();
+    let (client, admin, tokens) = setup_pool(&env);
+    let user = soroban_sdk::Address::random(&env);
+    fund_user(
+        &env,
+        &client,
+        &admin,
+        &tokens,
+        &user,
+        &[to_stroop(10), 0, 0],
+    );
+
+    client.swap_exact_amount_in(
+        &tokens[0].address(),
+        &to_stroop(1),
+        &tokens[1].address(),
+        &0,
+        &i128::MAX,
+        &user,
+        &None,
+        &0,
+    );
+    let (topics, _) = last_pool_event(&env, &client, Symbol::short("SWAP_V1"));
+    let expected: Vec<RawVal> = (
+        Symbol::short("SWAP_V1"),
+        user.clone(),
+        tokens[0].address(),
+        tokens[1].address(),
+    )
+        .into_val(&env);
+    assert_eq!(topics, expected);
+
+    let pool_amount_out =
+        client.dep_tokn_amt_in_get_lp_tokns_out(&tokens[0].address(), &to_stroop(1), &0, &user);
+    let (topics, data) = last_pool_event(&env, &client, Symbol::short("JOIN_V1"));
+    let expected: Vec<RawVal> =
+        (Symbol::short("JOIN_V1"), user.clone(), tokens[0].address()).into_val(&env);
+    assert_eq!(topics, expected);
+    assert_eq!(
+        JoinEvent::try_from_val(&env, &data).unwrap(),
+        JoinEvent {
+            caller: user.clone(),
+            token_in: tokens[0].address(),
+            token_amount_in: to_stroop(1),
+            pool_amount_out,
+        }
+    );
+
+    // The legacy format goes back to the fixed topics and the old bodies
+    assert_eq!(
+        client.try_set_legacy_events(&user, &true),
+        Err(Ok(Error::MissingRole))
+    );
+    client.set_legacy_events(&admin, &true);
+    assert!(client.get_legacy_events());
+    client.dep_tokn_amt_in_get_lp_tokns_out(&tokens[0].address(), &to_stroop(1), &0, &user);
+    let (topics, data) = last_pool_event(&env, &client, Symbol::short("LOG"));
+    let expected: Vec<RawVal> = (Symbol::short("LOG"), Symbol::short("JOIN")).into_val(&env);
+    assert_eq!(topics, expected);
+    assert_eq!(
+        LegacyJoinEvent::try_from_val(&env, &data).unwrap(),
+        LegacyJoinEvent {
+            caller: user.clone(),
+            token_in: tokens[0].address(),
+            token_amount_in: to_stroop(1),
+        }
+    );
+
+    let (token_amount_out, _) = client.swap_exact_amount_in(
+        &tokens[0].address(),
+        &to_stroop(1),
+        &tokens[1].address(),
+        &0,
+        &i128::MAX,
+        &user,
+        &None,
+        &0,
+    );
+    let (topics, data) = last_pool_event(&env, &client, Symbol::short("LOG"));
+    let expected: Vec<RawVal> = (Symbol::short("LOG"), Symbol::short("SWAP")).into_val(&env);
+    assert_eq!(topics, expected);
+    assert_eq!(
+        LegacySwapEvent::try_from_val(&env, &data).unwrap(),
+        LegacySwapEvent {
+            caller: user,
+            token_in: tokens[0].address(),
+            token_out: tokens[1].address(),
+            token_amount_in: to_stroop(1),
+            token_amount_out,
+        }
+    );
+}
+
+#[test]
+fn test_snapshot_event() {
+    let env = Env::default();
+    let (client, admin, tokens) = setup_pool(&env);
+    let user = soroban_sdk::Address::random(&env);
+    fund_user(&env, &client, &admin, &tokens, &user, &[0, to_stroop(2), 0]);
+
+    client.swap_exact_amount_in(
+        &tokens[1].address(),
+        &to_stroop(1),
+        &tokens[2].address(),
+        &0,
+        &i128::MAX,
+        &user,
+        &None,
+        &0,
+    );
+    let (_, data) = last_pool_event(&env, &client, Symbol::short("SNAP_V1"));
+    let snapshot = SnapshotEvent::try_from_val(&env, &data).unwrap();
+    assert_eq!(snapshot.tokens, client.get_current_tokens());
+    for (i, token) in tokens.iter().enumerate() {
+        assert_eq!(
+            snapshot.balances.get(i as u32).unwrap().unwrap(),
+            client.get_balance(&token.address())
+        );
+        assert_eq!(
+            snapshot.weights.get(i as u32).unwrap().unwrap(),
+            client.get_denormalized_weight(&token.address())
+        );
+    }
+    assert_eq!(snapshot.total_shares, 100 * BONE);
+
+    // Only the swapped tokens moved against the first token
+    assert_eq!(snapshot.prices.len(), 2);
+    for token in &tokens[1..] {
+        assert_eq!(
+            snapshot.prices.get(token.address()).unwrap().unwrap(),
+            client.get_spot_price_sans_fee(&tokens[0].address(), &token.address())
+        );
+    }
+}
+
+#[test]
+fn test_snapshot_after_exact_token_exit() {
+    let env = Env::default();
+    let (client, admin, tokens) = setup_pool(&env);
+    let contract_address = Address::from_contract_id(&env, &client.contract_id);
+
+    client.wdr_tokn_amt_out_get_lp_tokns_in(
+        &tokens[0].address(),
+        &to_stroop(5),
+        &i128::MAX,
+        &admin,
+    );
+    let (_, data) = last_pool_event(&env, &client, Symbol::short("SNAP_V1"));
+    let snapshot = SnapshotEvent::try_from_val(&env, &data).unwrap();
+    assert_eq!(client.get_balance(&tokens[0].address()), to_stroop(45));
+    assert_eq!(tokens[0].balance(&contract_address), to_stroop(45));
+    for (i, token) in tokens.iter().enumerate() {
+        assert_eq!(
+            snapshot.balances.get(i as u32).unwrap().unwrap(),
+            client.get_balance(&token.address())
+        );
+    }
+}
+
+#[test]
+fn test_get_position() {
+    let env = Env::default();
+    let (client, admin, tokens) = setup_pool(&env);
+    let user = soroban_sdk::Address::random(&env);
+
+    // Everything but the locked liquidity belongs to the controller
+    let position = client.get_position(&admin, &tokens[0].address());
+    assert_eq!(position.balance, 100 * BONE - MIN_LIQUIDITY);
+    assert_eq!(position.share, to_stroop(0.99999));
+    assert_eq!(
+        position.tokens,
+        vec![
+            &env,
+            tokens[0].address(),
+            tokens[1].address(),
+            tokens[2].address()
+        ]
+    );
+    assert_eq!(
+        position.amounts,
+        vec![
+            &env,
+            to_stroop(49.9995),
+            to_stroop(19.9998),
+            to_stroop(9999.9)
+        ]
+    );
+    // Equal weights, so each token is worth the same in the numeraire
+    assert_eq!(position.value, 3 * to_stroop(49.9995));
+
+    let position = client.get_position(&user, &tokens[0].address());
+    assert_eq!(position.balance, 0);
+    assert_eq!(position.amounts, vec![&env, 0, 0, 0]);
+    assert_eq!(position.value, 0);
+}
+
+#[test]
+fn test_fees_earned() {
+    let env = Env::default();
+    let (client, admin, tokens) = setup_pool(&env);
+    let contract_share: [u8; 32] = client.share_id().into();
+    let token_share = token::Client::new(&env, &contract_share);
+    let user = soroban_sdk::Address::random(&env);
+    let lp = soroban_sdk::Address::random(&env);
+    fund_user(&env, &client, &admin, &tokens, &user, &[to_stroop(5), 0, 0]);
+
+    let swap = || {
+        client.swap_exact_amount_in(
+            &tokens[0].address(),
+            &to_stroop(1),
+            &tokens[2].address(),
+            &0,
+            &i128::MAX,
+            &user,
+            &None,
+            &0,
+        );
+    };
+
+    // A 0.3% fee on 1 token in, nearly all of it owed to the controller's shares
+    swap();
+    assert_eq!(client.get_fees_earned(&admin), vec![&env, 29999, 0, 0]);
+    assert_eq!(client.get_fees_earned(&lp), vec![&env, 0, 0, 0]);
+
+    // A transfer checkpoints both sides, the new LP only earns from here on
+    token_share.xfer(&admin, &lp, &to_stroop(50));
+    assert_eq!(client.get_fees_earned(&admin), vec![&env, 29999, 0, 0]);
+    assert_eq!(client.get_fees_earned(&lp), vec![&env, 0, 0, 0]);
+
+    swap();
+    assert_eq!(
+        client.get_fees_earned(&admin),
+        vec![&env, 29999 + 14999, 0, 0]
+    );
+    assert_eq!(client.get_fees_earned(&lp), vec![&env, 15000, 0, 0]);
+}
+
+#[test]
+fn test_fees_earned_exact_tokens() {
+    let env = Env::default();
+    let (client, admin, tokens) = setup_pool(&env);
+    let user = soroban_sdk::Address::random(&env);
+    fund_user(&env, &client, &admin, &tokens, &user, &[to_stroop(1), 0, 0]);
+
+    // Two thirds of the deposit is a swap into the other tokens and pays the 0.3% fee
+    client.join_exact_tokens_in(&vec![&env, to_stroop(1), 0, 0], &0, &user);
+    assert_eq!(client.get_fees_earned(&admin), vec![&env, 19999, 0, 0]);
+    assert_eq!(client.get_fees_earned(&user), vec![&env, 0, 0, 0]);
+
+    client.exit_exact_tokens_out(&vec![&env, 0, 0, to_stroop(100)], &i128::MAX, &admin);
+    let fees = client.get_fees_earned(&user);
+    assert_eq!(fees.get(0).unwrap().unwrap(), 0);
+    assert!(fees.get(2).unwrap().unwrap() > 0);
+}
+
+// Signs a permit for the pool's LP token with `keypair`
+fn sign_permit(env: &Env, keypair: &ed25519_dalek::Keypair, payload: &PermitPayload) -> BytesN<64> {
+    use ed25519_dalek::Signer;
+    let msg: std::vec::Vec<u8> = payload.clone().serialize(env).iter().collect();
+    BytesN::from_array(env, &keypair.sign(&msg).to_bytes())
+}
+
+#[test]
+fn test_permit() {
+    let env = Env::default();
+    let (client, _, _) = setup_pool(&env);
+    let contract_address = Address::from_contract_id(&env, &client.contract_id);
+    let owner = soroban_sdk::Address::random(&env);
+    let spender = soroban_sdk::Address::random(&env);
+
+    let keypair = ed25519_dalek::Keypair::generate(&mut rand::thread_rng());
+    client.set_permit_key(
+        &owner,
+        &BytesN::from_array(&env, &keypair.public.to_bytes()),
+    );
+
+    let expiry = env.ledger().sequence() + 10;
+    let payload = PermitPayload {
+        token: contract_address,
+        owner: owner.clone(),
+        spender: spender.clone(),
+        amount: to_stroop(3),
+        nonce: client.nonce(&owner),
+        expiry,
+    };
+    let signature = sign_permit(&env, &keypair, &payload);
+
+    // Anyone can relay the signed payload
+    client.permit(&owner, &spender, &to_stroop(3), &expiry, &signature);
+    assert_eq!(client.allowance(&owner, &spender), to_stroop(3));
+    assert_eq!(client.nonce(&owner), 1);
+
+    // The nonce moved on, so the same signature can't be replayed
+    let replay = client.try_permit(&owner, &spender, &to_stroop(3), &expiry, &signature);
+    assert!(replay.is_err());
+}
+
+#[test]
+fn test_permit_expired() {
+    let env = Env::default();
+    let (client, _, _) = setup_pool(&env);
+    let contract_address = Address::from_contract_id(&env, &client.contract_id);
+    let owner = soroban_sdk::Address::random(&env);
+    let spender = soroban_sdk::Address::random(&env);
+
+    let keypair = ed25519_dalek::Keypair::generate(&mut rand::thread_rng());
+    client.set_permit_key(
+        &owner,
+        &BytesN::from_array(&env, &keypair.public.to_bytes()),
+    );
+
+    let expiry = env.ledger().sequence() + 10;
+    let payload = PermitPayload {
+        token: contract_address,
+        owner: owner.clone(),
+        spender: spender.clone(),
+        amount: to_stroop(3),
+        nonce: 0,
+        expiry,
+    };
+    let signature = sign_permit(&env, &keypair, &payload);
+
+    env.ledger().with_mut(|li| li.sequence_number = expiry + 1);
+    let result = client.try_permit(&owner, &spender, &to_stroop(3), &expiry, &signature);
+    assert_eq!(result, Err(Ok(Error::PermitExpired)));
+
+    // Owners that never registered a key can't permit
+    let result = client.try_permit(&spender, &owner, &to_stroop(3), &(expiry + 10), &signature);
+    assert_eq!(result, Err(Ok(Error::NoPermitKey)));
+}
+
+#[test]
+fn test_approve_with_expiration() {
+    let env = Env::default();
+    let (client, admin, _) = setup_pool(&env);
+    let spender = soroban_sdk::Address::random(&env);
+    let other = soroban_sdk::Address::random(&env);
+
+    let expiration_ledger = env.ledger().sequence() + 10;
+    env.ledger().with_mut(|li| li.sequence_number += 1);
+    assert_eq!(
+        client.try_approve(
+            &admin,
+            &spender,
+            &to_stroop(5),
+            &(env.ledger().sequence() - 1)
+        ),
+        Err(Ok(Error::ExpirationInPast))
+    );
+    assert_eq!(
+        client.try_approve(&admin, &spender, &-1, &expiration_ledger),
+        Err(Ok(Error::NegativeAmount))
+    );
+    client.approve(&admin, &spender, &to_stroop(5), &expiration_ledger);
+    assert_eq!(client.allowance(&admin, &spender), to_stroop(5));
+
+    // Increasing keeps the expiration, spending works until it's reached
+    client.incr_allow(&admin, &spender, &to_stroop(1));
+    let (_, data) = last_pool_event(&env, &client, Symbol::new(&env, "incr_allow"));
+    assert_eq!(
+        <(i128, u32)>::try_from_val(&env, &data).unwrap(),
+        (to_stroop(1), expiration_ledger)
+    );
+    client.xfer_from(&spender, &admin, &other, &to_stroop(2));
+    assert_eq!(client.allowance(&admin, &spender), to_stroop(4));
+
+    env.ledger()
+        .with_mut(|li| li.sequence_number = expiration_ledger + 1);
+    assert_eq!(client.allowance(&admin, &spender), 0);
+    assert!(client
+        .try_xfer_from(&spender, &admin, &other, &to_stroop(1))
+        .is_err());
+
+    // Allowances from incr_allow don't expire
+    client.incr_allow(&admin, &spender, &to_stroop(3));
+    env.ledger()
+        .with_mut(|li| li.sequence_number = expiration_ledger + 1000);
+    assert_eq!(client.allowance(&admin, &spender), to_stroop(3));
+}
+
+#[test]
+fn test_legacy_allowance() {
+    let env = Env::default();
+    let (client, admin, _) = setup_pool(&env);
+    let spender = soroban_sdk::Address::random(&env);
+    let other = soroban_sdk::Address::random(&env);
+
+    // Allowance stored as a plain i128 before allowances could expire
+    let legacy_key = DataKeyToken::Allowance(AllowanceDataKey {
+        from: admin.clone(),
+        spender: spender.clone(),
+    });
+    env.as_contract(&client.contract_id, || {
+        env.storage().set(&legacy_key, &to_stroop(5));
+    });
+    env.ledger().with_mut(|li| li.sequence_number += 1000);
+    assert_eq!(client.allowance(&admin, &spender), to_stroop(5));
+
+    client.xfer_from(&spender, &admin, &other, &to_stroop(2));
+    assert_eq!(client.allowance(&admin, &spender), to_stroop(3));
+    client.decr_allow(&admin, &spender, &to_stroop(1));
+    assert_eq!(client.allowance(&admin, &spender), to_stroop(2));
+    env.as_contract(&client.contract_id, || {
+        assert!(!env.storage().has(&legacy_key));
+    });
+}
+
+#[test]
+fn test_balance_and_supply_checkpoints() {
+    let env = Env::default();
+    env.ledger().with_mut(|li| li.sequence_number = 100);
+    let (client, admin, tokens) = setup_pool(&env);
+    let user = soroban_sdk::Address::random(&env);
+    let controller_shares = 100 * BONE - MIN_LIQUIDITY;
+
+    env.ledger().with_mut(|li| li.sequence_number = 110);
+    client.xfer(&admin, &user, &to_stroop(10));
+    // Only the last change in a ledger is kept
+    client.xfer(&admin, &user, &to_stroop(10));
+
+    env.ledger().with_mut(|li| li.sequence_number = 120);
+    client.exit_pool(&to_stroop(5), &vec![&env, 0, 0, 0], &user);
+
+    assert_eq!(client.balance_at(&admin, &99), 0);
+    assert_eq!(client.balance_at(&admin, &100), controller_shares);
+    assert_eq!(client.balance_at(&admin, &109), controller_shares);
+    assert_eq!(
+        client.balance_at(&admin, &110),
+        controller_shares - to_stroop(20)
+    );
+    assert_eq!(client.balance_at(&user, &110), to_stroop(20));
+    assert_eq!(client.balance_at(&user, &120), to_stroop(15));
+    assert_eq!(client.balance_at(&user, &1000), to_stroop(15));
+
+    assert_eq!(client.total_supply_at(&99), 0);
+    assert_eq!(client.total_supply_at(&115), 100 * BONE);
+    assert_eq!(client.total_supply_at(&120), to_stroop(95));
+    assert!(client.get_balance(&tokens[0].address()) < to_stroop(50));
+
+    // Every checkpoint is found once the history grows past a few entries
+    for ledger in 130..140 {
+        env.ledger().with_mut(|li| li.sequence_number = ledger);
+        client.xfer(&user, &admin, &to_stroop(1));
+    }
+    for ledger in 130..140 {
+        assert_eq!(
+            client.balance_at(&user, &ledger),
+            to_stroop(15) - to_stroop(ledger - 129)
+        );
+    }
+    assert_eq!(client.balance_at(&user, &129), to_stroop(15));
+}
+
+#[test]
+fn test_vote_delegation() {
+    let env = Env::default();
+    env.ledger().with_mut(|li| li.sequence_number = 100);
+    let (client, admin, _) = setup_pool(&env);
+    let user = soroban_sdk::Address::random(&env);
+    let delegatee = soroban_sdk::Address::random(&env);
+    let controller_shares = 100 * BONE - MIN_LIQUIDITY;
+
+    // Balances only count once they're delegated
+    assert_eq!(client.get_votes(&admin), 0);
+    client.delegate(&admin, &admin);
+    assert_eq!(client.delegates(&admin), Some(admin.clone()));
+    assert_eq!(client.get_votes(&admin), controller_shares);
+
+    env.ledger().with_mut(|li| li.sequence_number = 110);
+    client.delegate(&user, &delegatee);
+    client.xfer(&admin, &user, &to_stroop(30));
+    assert_eq!(client.get_votes(&admin), controller_shares - to_stroop(30));
+    assert_eq!(client.get_votes(&delegatee), to_stroop(30));
+    assert_eq!(client.get_votes(&user), 0);
+
+    // Burning shares on exit takes the votes with them
+    env.ledger().with_mut(|li| li.sequence_number = 120);
+    client.exit_pool(&to_stroop(10), &vec![&env, 0, 0, 0], &user);
+    assert_eq!(client.get_votes(&delegatee), to_stroop(20));
+
+    // Redelegating moves the whole balance
+    env.ledger().with_mut(|li| li.sequence_number = 130);
+    client.delegate(&user, &admin);
+    assert_eq!(client.get_votes(&delegatee), 0);
+    assert_eq!(client.get_votes(&admin), controller_shares - to_stroop(10));
+
+    assert_eq!(client.get_past_votes(&delegatee, &105), 0);
+    assert_eq!(client.get_past_votes(&delegatee, &110), to_stroop(30));
+    assert_eq!(client.get_past_votes(&delegatee, &125), to_stroop(20));
+    assert_eq!(client.get_past_votes(&delegatee, &130), 0);
+    assert_eq!(client.get_past_votes(&admin, &100), controller_shares);
+}
+
+#[test]
+fn test_swap_fee_governance() {
+    let env = Env::default();
+    env.ledger().with_mut(|li| li.sequence_number = 100);
+    let (client, admin, _) = setup_pool(&env);
+    let user = soroban_sdk::Address::random(&env);
+    let latecomer = soroban_sdk::Address::random(&env);
+    client.delegate(&user, &user);
+    client.xfer(&admin, &user, &to_stroop(30));
+
+    env.ledger().with_mut(|li| li.sequence_number = 101);
+    let proposal_id = client.propose_swap_fee(&user, &to_stroop(0.005));
+    let proposal = client.get_fee_proposal(&proposal_id);
+    assert_eq!(proposal.snapshot_ledger, 100);
+
+    // Shares that were never delegated don't vote
+    assert_eq!(
+        client.try_vote_swap_fee(&admin, &proposal_id, &true),
+        Err(Ok(Error::NoVotingPower))
+    );
+
+    // Shares received after the snapshot don't vote
+    client.delegate(&latecomer, &latecomer);
+    client.xfer(&admin, &latecomer, &to_stroop(10));
+    assert_eq!(
+        client.try_vote_swap_fee(&latecomer, &proposal_id, &true),
+        Err(Ok(Error::NoVotingPower))
+    );
+
+    client.vote_swap_fee(&user, &proposal_id, &true);
+    assert_eq!(
+        client.try_vote_swap_fee(&user, &proposal_id, &true),
+        Err(Ok(Error::AlreadyVoted))
+    );
+    assert_eq!(
+        client.get_fee_proposal(&proposal_id).votes_for,
+        to_stroop(30)
+    );
+
+    // Passed, but the fee only changes once the delay is over
+    env.ledger()
+        .with_mut(|li| li.sequence_number = proposal.end_ledger + 1);
+    assert_eq!(
+        client.try_execute_swap_fee(&proposal_id),
+        Err(Ok(Error::Timelock))
+    );
+    assert_eq!(client.get_swap_fee(), to_stroop(0.003));
+
+    env.ledger()
+        .with_mut(|li| li.sequence_number = proposal.end_ledger + GOV_EXECUTION_DELAY + 1);
+    client.execute_swap_fee(&proposal_id);
+    assert_eq!(client.get_swap_fee(), to_stroop(0.005));
+    assert!(client.get_fee_proposal(&proposal_id).executed);
+}
+
+#[test]
+fn test_swap_fee_governance_defeated() {
+    let env = Env::default();
+    env.ledger().with_mut(|li| li.sequence_number = 100);
+    let (client, admin, _) = setup_pool(&env);
+    let user = soroban_sdk::Address::random(&env);
+    client.delegate(&admin, &admin);
+    client.delegate(&user, &user);
+    client.xfer(&admin, &user, &to_stroop(30));
+
+    env.ledger().with_mut(|li| li.sequence_number = 101);
+    let proposal_id = client.propose_swap_fee(&user, &to_stroop(0.005));
+    client.vote_swap_fee(&user, &proposal_id, &true);
+    client.vote_swap_fee(&admin, &proposal_id, &false);
+
+    let proposal = client.get_fee_proposal(&proposal_id);
+    env.ledger()
+        .with_mut(|li| li.sequence_number = proposal.end_ledger + GOV_EXECUTION_DELAY + 1);
+    let result = client.try_execute_swap_fee(&proposal_id);
+    assert_eq!(result, Err(Ok(Error::Defeated)));
+}
+
+#[test]
+fn test_swap_fee_governance_quorum() {
+    let env = Env::default();
+    env.ledger().with_mut(|li| li.sequence_number = 100);
+    let (client, admin, _) = setup_pool(&env);
+    let user = soroban_sdk::Address::random(&env);
+    // The MIN_LIQUIDITY shares locked in the pool don't count towards the quorum
+    let quorum = (100 * BONE - MIN_LIQUIDITY) / 10;
+    client.delegate(&user, &user);
+    client.xfer(&admin, &user, &(quorum - 1));
+
+    env.ledger().with_mut(|li| li.sequence_number = 101);
+    let proposal_id = client.propose_swap_fee(&user, &to_stroop(0.005));
+    client.vote_swap_fee(&user, &proposal_id, &true);
+    let proposal = client.get_fee_proposal(&proposal_id);
+    env.ledger()
+        .with_mut(|li| li.sequence_number = proposal.end_ledger + GOV_EXECUTION_DELAY + 1);
+    let result = client.try_execute_swap_fee(&proposal_id);
+    assert_eq!(result, Err(Ok(Error::Quorum)));
+
+    client.xfer(&admin, &user, &1);
+    env.ledger().with_mut(|li| li.sequence_number += 1);
+    let proposal_id = client.propose_swap_fee(&user, &to_stroop(0.005));
+    client.vote_swap_fee(&user, &proposal_id, &true);
+    let proposal = client.get_fee_proposal(&proposal_id);
+    env.ledger()
+        .with_mut(|li| li.sequence_number = proposal.end_ledger + GOV_EXECUTION_DELAY + 1);
+    client.execute_swap_fee(&proposal_id);
+    assert_eq!(client.get_swap_fee(), to_stroop(0.005));
+}
+
+#[test]
+fn test_swap_fee_governance_expired() {
+    let env = Env::default();
+    env.ledger().with_mut(|li| li.sequence_number = 100);
+    let (client, admin, _) = setup_pool(&env);
+    let user = soroban_sdk::Address::random(&env);
+    client.delegate(&user, &user);
+    client.xfer(&admin, &user, &to_stroop(30));
+
+    env.ledger().with_mut(|li| li.sequence_number = 101);
+    let proposal_id = client.propose_swap_fee(&user, &to_stroop(0.005));
+    client.vote_swap_fee(&user, &proposal_id, &true);
+
+    let proposal = client.get_fee_proposal(&proposal_id);
+    env.ledger().with_mut(|li| {
+        li.sequence_number = proposal.end_ledger + GOV_EXECUTION_DELAY + GOV_EXECUTION_WINDOW + 1
+    });
+    let result = client.try_execute_swap_fee(&proposal_id);
+    assert_eq!(result, Err(Ok(Error::Expired)));
+    assert_eq!(client.get_swap_fee(), to_stroop(0.003));
+}
+
+#[test]
+fn test_swap_fee_governance_fee_changed() {
+    let env = Env::default();
+    env.ledger().with_mut(|li| li.sequence_number = 100);
+    let (client, admin, _) = setup_pool(&env);
+    let user = soroban_sdk::Address::random(&env);
+    client.delegate(&user, &user);
+    client.xfer(&admin, &user, &to_stroop(30));
+
+    env.ledger().with_mut(|li| li.sequence_number = 101);
+    let proposal_id = client.propose_swap_fee(&user, &to_stroop(0.005));
+    client.vote_swap_fee(&user, &proposal_id, &true);
+
+    // The fee manager ramped the fee while the proposal was pending
+    client.ramp_swap_fee(&admin, &to_stroop(0.006), &(env.ledger().timestamp() + 100));
+
+    let proposal = client.get_fee_proposal(&proposal_id);
+    env.ledger()
+        .with_mut(|li| li.sequence_number = proposal.end_ledger + GOV_EXECUTION_DELAY + 1);
+    let result = client.try_execute_swap_fee(&proposal_id);
+    assert_eq!(result, Err(Ok(Error::FeeChanged)));
+}
+
+#[test]
+fn test_ramp_swap_fee() {
+    let env = Env::default();
+    env.ledger().with_mut(|li| li.timestamp = 1000);
+    let (client, admin, _) = setup_pool(&env);
+    assert_eq!(client.get_swap_fee_ramp(), None);
+
+    client.ramp_swap_fee(&admin, &to_stroop(0.006), &1100);
+    assert_eq!(
+        client.get_swap_fee_ramp(),
+        Some(FeeRamp {
+            start_fee: to_stroop(0.003),
+            target_fee: to_stroop(0.006),
+            start_time: 1000,
+            end_time: 1100,
+        })
+    );
+    assert_eq!(client.get_swap_fee(), to_stroop(0.003));
+
+    env.ledger().with_mut(|li| li.timestamp = 1050);
+    assert_eq!(client.get_swap_fee(), to_stroop(0.0045));
+
+    // A new ramp starts from wherever the current one got to
+    client.ramp_swap_fee(&admin, &to_stroop(0.001), &1150);
+    env.ledger().with_mut(|li| li.timestamp = 1100);
+    assert_eq!(client.get_swap_fee(), to_stroop(0.00275));
+
+    env.ledger().with_mut(|li| li.timestamp = 2000);
+    assert_eq!(client.get_swap_fee(), to_stroop(0.001));
+}
+
+#[test]
+fn test_roles() {
+    let env = Env::default();
+    let (client, admin, _) = setup_pool(&env);
+    let fee_manager = soroban_sdk::Address::random(&env);
+    let user = soroban_sdk::Address::random(&env);
+
+    // The controller holds every role
+    assert!(client.has_role(&Role::Admin, &admin));
+    assert!(client.has_role(&Role::Pauser, &admin));
+    assert!(!client.has_role(&Role::FeeManager, &fee_manager));
+    assert_eq!(
+        client.try_set_fee_multiplier(&fee_manager, &user, &(BONE / 2)),
+        Err(Ok(Error::MissingRole))
+    );
+
+    client.grant_role(&admin, &Role::FeeManager, &fee_manager);
+    assert!(client.has_role(&Role::FeeManager, &fee_manager));
+    assert!(!client.has_role(&Role::Pauser, &fee_manager));
+    client.set_fee_multiplier(&fee_manager, &user, &(BONE / 2));
+    assert_eq!(client.get_effective_fee(&user), to_stroop(0.0015));
+    assert_eq!(
+        client.try_set_public_swap(&fee_manager, &false),
+        Err(Ok(Error::MissingRole))
+    );
+    // Only admins grant roles
+    assert_eq!(
+        client.try_grant_role(&fee_manager, &Role::Pauser, &fee_manager),
+        Err(Ok(Error::MissingRole))
+    );
+
+    client.renounce_role(&fee_manager, &Role::FeeManager);
+    assert!(!client.has_role(&Role::FeeManager, &fee_manager));
+
+    client.grant_role(&admin, &Role::Admin, &user);
+    assert!(client.has_role(&Role::Rescuer, &user));
+    client.revoke_role(&admin, &Role::Admin, &user);
+    assert!(!client.has_role(&Role::Rescuer, &user));
+}
+
+#[test]
+fn test_rescue_token() {
+    let env = Env::default();
+    let (client, admin, tokens) = setup_pool(&env);
+    let contract_address = Address::from_contract_id(&env, &client.contract_id);
+    let rescuer = soroban_sdk::Address::random(&env);
+    let user = soroban_sdk::Address::random(&env);
+    client.grant_role(&admin, &Role::Rescuer, &rescuer);
+
+    // Tokens sent straight to the pool aren't part of its balances
+    tokens[0].mint(&admin, &contract_address, &to_stroop(2));
+    let stray = create_token_contract(&env, &admin);
+    stray.mint(&admin, &contract_address, &to_stroop(7));
+
+    assert_eq!(
+        client.try_rescue_token(&rescuer, &tokens[0].address(), &user, &to_stroop(3)),
+        Err(Ok(Error::RescueAmount))
+    );
+    client.rescue_token(&rescuer, &tokens[0].address(), &user, &to_stroop(2));
+    client.rescue_token(&rescuer, &stray.address(), &user, &to_stroop(7));
+    assert_eq!(tokens[0].balance(&user), to_stroop(2));
+    assert_eq!(stray.balance(&user), to_stroop(7));
+    assert_eq!(tokens[0].balance(&contract_address), to_stroop(50));
+    assert_eq!(client.get_balance(&tokens[0].address()), to_stroop(50));
+
+    // The pool's own LP tokens, locked liquidity included, stay put
+    assert_eq!(
+        client.try_rescue_token(&rescuer, &contract_address, &user, &MIN_LIQUIDITY),
+        Err(Ok(Error::RescueShares))
+    );
+    assert_eq!(client.balance(&contract_address), MIN_LIQUIDITY);
+    assert_eq!(
+        client.try_rescue_token(&rescuer, &stray.address(), &user, &-1),
+        Err(Ok(Error::NegativeAmount))
+    );
+}
+
+#[test]
+fn test_finalize_requires_admin() {
+    let env = Env::default();
+    let (client, _, _) = setup_bound_pool(&env, None);
+    let stranger = soroban_sdk::Address::random(&env);
+    assert_eq!(client.try_finalize(&stranger), Err(Ok(Error::MissingRole)));
+}
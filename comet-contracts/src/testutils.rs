@@ -0,0 +1,49 @@
+#![cfg(any(test, feature = "testutils"))]
+
+extern crate std;
+
+use crate::c_pool::contract::{CometPoolContract, CometPoolContractClient};
+use soroban_sdk::{Address, BytesN, Env};
+
+pub mod token {
+    soroban_sdk::contractimport!(file = "../soroban_token_spec.wasm");
+}
+
+pub fn create_token_contract(e: &Env, admin: &Address) -> token::Client {
+    token::Client::new(e, &e.register_stellar_asset_contract(admin.clone()))
+}
+
+// Three token pool with a 0.3% swap fee, left unfinalized. `admin` controls
+// both the pool and the underlying tokens.
+pub fn create_bound_pool(
+    e: &Env,
+    admin: &Address,
+    hooks: Option<Address>,
+) -> (CometPoolContractClient, std::vec::Vec<token::Client>) {
+    let contract_id = e.register_contract(None, CometPoolContract);
+    let client = CometPoolContractClient::new(e, &contract_id);
+    client.init(admin, admin, &BytesN::from_array(e, &[0; 32]), &hooks);
+    let contract_address = Address::from_contract_id(e, &contract_id);
+
+    let mut tokens = std::vec::Vec::new();
+    for balance in [500_000_000, 200_000_000, 100_000_000_000] {
+        let token = create_token_contract(e, admin);
+        token.mint(admin, admin, &balance);
+        token.incr_allow(admin, &contract_address, &i128::MAX);
+        client.bind(&token.address(), &balance, &50_000_000, admin);
+        tokens.push(token);
+    }
+    client.set_swap_fee(&30_000, admin);
+    (client, tokens)
+}
+
+// Same pool as `create_bound_pool`, finalized. `admin` holds all the LP tokens
+// that aren't locked in the pool.
+pub fn create_pool(
+    e: &Env,
+    admin: &Address,
+) -> (CometPoolContractClient, std::vec::Vec<token::Client>) {
+    let (client, tokens) = create_bound_pool(e, admin, None);
+    client.finalize(admin);
+    (client, tokens)
+}
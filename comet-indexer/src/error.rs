@@ -0,0 +1,22 @@
+use std::fmt;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Error {
+    Base64(String), // input isn't valid base64
+    Xdr(String),    // bytes don't decode to the expected XDR type
+    Json(String),   // malformed RPC event
+    Decode(String), // a pool event whose topics or body don't match its type
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Base64(msg) => write!(f, "invalid base64: {}", msg),
+            Error::Xdr(msg) => write!(f, "invalid xdr: {}", msg),
+            Error::Json(msg) => write!(f, "invalid json event: {}", msg),
+            Error::Decode(msg) => write!(f, "undecodable pool event: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
@@ -0,0 +1,384 @@
+use std::fmt;
+
+use soroban_sdk::xdr::{
+    AccountId, ContractEvent, ContractEventBody, ContractEventType, ContractEventV0,
+    ExtensionPoint, Hash, PublicKey, ReadXdr, ScAddress, ScVal, ScVec, Uint256, WriteXdr,
+};
+
+use crate::error::Error;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Address {
+    Account([u8; 32]),  // ed25519 public key
+    Contract([u8; 32]), // contract id
+}
+
+impl Address {
+    pub fn from_sc_val(val: &ScVal) -> Result<Self, Error> {
+        match val {
+            ScVal::Address(ScAddress::Contract(Hash(id))) => Ok(Address::Contract(*id)),
+            ScVal::Address(ScAddress::Account(AccountId(PublicKey::PublicKeyTypeEd25519(
+                Uint256(key),
+            )))) => Ok(Address::Account(*key)),
+            _ => Err(Error::Decode(format!("expected an address, got {:?}", val))),
+        }
+    }
+
+    pub fn contract_from_hex(id: &str) -> Option<Self> {
+        from_hex(id).map(Address::Contract)
+    }
+}
+
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Address::Account(key) => write!(f, "account:{}", to_hex(key)),
+            Address::Contract(id) => write!(f, "contract:{}", to_hex(id)),
+        }
+    }
+}
+
+// A contract event as it comes off the ledger, before it's matched against the pool's events
+#[derive(Clone, Debug, PartialEq)]
+pub struct RawEvent {
+    pub contract_id: Option<[u8; 32]>,
+    pub topics: Vec<ScVal>,
+    pub data: ScVal,
+}
+
+impl RawEvent {
+    // Base64 XDR of a ContractEvent
+    pub fn from_xdr_base64(input: &str) -> Result<Self, Error> {
+        let bytes = base64::decode(input.trim()).map_err(|e| Error::Base64(e.to_string()))?;
+        let event = ContractEvent::from_xdr(bytes).map_err(|e| Error::Xdr(e.to_string()))?;
+        let ContractEventBody::V0(body) = event.body;
+        Ok(RawEvent {
+            contract_id: event.contract_id.map(|id| id.0),
+            topics: body.topics.0.to_vec(),
+            data: body.data,
+        })
+    }
+
+    pub fn to_xdr_base64(&self) -> Result<String, Error> {
+        let topics = self
+            .topics
+            .clone()
+            .try_into()
+            .map_err(|_| Error::Xdr("too many topics".to_string()))?;
+        let event = ContractEvent {
+            ext: ExtensionPoint::V0,
+            contract_id: self.contract_id.map(Hash),
+            type_: ContractEventType::Contract,
+            body: ContractEventBody::V0(ContractEventV0 {
+                topics: ScVec(topics),
+                data: self.data.clone(),
+            }),
+        };
+        let bytes = event.to_xdr().map_err(|e| Error::Xdr(e.to_string()))?;
+        Ok(base64::encode(bytes))
+    }
+
+    // An event as soroban-rpc's getEvents returns it, a hex `contractId` with base64 XDR
+    // ScVals for `topic` and `value`
+    pub fn from_json(input: &str) -> Result<Self, Error> {
+        let json: serde_json::Value =
+            serde_json::from_str(input).map_err(|e| Error::Json(e.to_string()))?;
+        let contract_id = match json.get("contractId").and_then(|id| id.as_str()) {
+            Some(id) => {
+                Some(from_hex(id).ok_or_else(|| Error::Json(format!("bad contractId {}", id)))?)
+            }
+            None => None,
+        };
+        let topics = json
+            .get("topic")
+            .and_then(|topics| topics.as_array())
+            .ok_or_else(|| Error::Json("missing topic".to_string()))?
+            .iter()
+            .map(sc_val_from_json)
+            .collect::<Result<Vec<ScVal>, Error>>()?;
+        // Older RPC versions wrap the value as {"xdr": ...}
+        let value = json
+            .get("value")
+            .ok_or_else(|| Error::Json("missing value".to_string()))?;
+        let data = sc_val_from_json(value.get("xdr").unwrap_or(value))?;
+        Ok(RawEvent {
+            contract_id,
+            topics,
+            data,
+        })
+    }
+
+    // One line of an event file, either JSON or base64 XDR
+    pub fn parse(line: &str) -> Result<Self, Error> {
+        if line.trim_start().starts_with('{') {
+            Self::from_json(line)
+        } else {
+            Self::from_xdr_base64(line)
+        }
+    }
+}
+
+fn sc_val_from_json(val: &serde_json::Value) -> Result<ScVal, Error> {
+    let encoded = val
+        .as_str()
+        .ok_or_else(|| Error::Json(format!("expected a base64 string, got {}", val)))?;
+    let bytes = base64::decode(encoded).map_err(|e| Error::Base64(e.to_string()))?;
+    ScVal::from_xdr(bytes).map_err(|e| Error::Xdr(e.to_string()))
+}
+
+// The pool's events an indexer replays, decoded from their topics and bodies
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PoolEvent {
+    Bind {
+        token: Address,
+        balance: i128,
+        denorm: i128,
+    },
+    Rebind {
+        token: Address,
+        balance: i128,
+        denorm: i128,
+    },
+    Finalize {
+        supply: i128,
+    },
+    SwapFee {
+        fee: i128,
+    },
+    FeeRamp {
+        start_fee: i128,
+        target_fee: i128,
+        start_time: u64,
+        end_time: u64,
+    },
+    FeeProposalExecuted {
+        id: u32,
+        fee: i128,
+    },
+    Swap {
+        caller: Address,
+        token_in: Address,
+        token_out: Address,
+        token_amount_in: i128, // what reached the pool, after the referral fee
+        token_amount_out: i128,
+        referral_amount: Option<i128>, // missing from legacy events
+    },
+    Join {
+        caller: Address,
+        token_in: Address,
+        token_amount_in: i128,
+        pool_amount_out: Option<i128>, // missing from legacy events
+    },
+    Exit {
+        caller: Address,
+        token_out: Address,
+        token_amount_out: i128,
+        pool_amount_in: Option<i128>, // missing from legacy events
+    },
+    Snapshot {
+        tokens: Vec<Address>,
+        balances: Vec<i128>,
+        weights: Vec<i128>,
+        total_shares: i128,
+    },
+    // LP token events, the pool is its own share token
+    Mint {
+        to: Address,
+        amount: i128,
+    },
+    Transfer {
+        from: Address,
+        to: Address,
+        amount: i128,
+    },
+    Burn {
+        from: Address,
+        amount: i128,
+    },
+    Clawback {
+        from: Address,
+        amount: i128,
+    },
+}
+
+impl PoolEvent {
+    // None for events the pool's state doesn't depend on, like role changes or fee votes
+    pub fn decode(event: &RawEvent) -> Result<Option<Self>, Error> {
+        let name = match event.topics.first().and_then(symbol) {
+            Some(name) => name,
+            None => return Ok(None),
+        };
+        let data = &event.data;
+        let decoded = match name.as_str() {
+            "SWAP_V1" => Self::decode_swap(data)?,
+            "JOIN_V1" => Self::decode_join(data)?,
+            "EXIT_V1" => Self::decode_exit(data)?,
+            "SNAP_V1" => PoolEvent::Snapshot {
+                tokens: as_vec(field(data, "tokens")?)?
+                    .iter()
+                    .map(Address::from_sc_val)
+                    .collect::<Result<_, _>>()?,
+                balances: as_vec(field(data, "balances")?)?
+                    .iter()
+                    .map(int)
+                    .collect::<Result<_, _>>()?,
+                weights: as_vec(field(data, "weights")?)?
+                    .iter()
+                    .map(int)
+                    .collect::<Result<_, _>>()?,
+                total_shares: int(field(data, "total_shares")?)?,
+            },
+            "LOG" => {
+                let action = event.topics.get(1).and_then(symbol).unwrap_or_default();
+                match action.as_str() {
+                    "SWAP" => Self::decode_swap(data)?,
+                    "JOIN" => Self::decode_join(data)?,
+                    "EXIT" => Self::decode_exit(data)?,
+                    "BIND" => PoolEvent::Bind {
+                        token: Address::from_sc_val(field(data, "token")?)?,
+                        balance: int(field(data, "balance")?)?,
+                        denorm: int(field(data, "denorm")?)?,
+                    },
+                    "REBIND" => PoolEvent::Rebind {
+                        token: Address::from_sc_val(field(data, "token")?)?,
+                        balance: int(field(data, "new_balance")?)?,
+                        denorm: int(field(data, "new_denorm")?)?,
+                    },
+                    "FINALIZE" => PoolEvent::Finalize {
+                        supply: int(field(data, "supply")?)?,
+                    },
+                    "SWAP_FEE" => PoolEvent::SwapFee {
+                        fee: int(field(data, "new_fee")?)?,
+                    },
+                    "FEE_RAMP" => PoolEvent::FeeRamp {
+                        start_fee: int(field(data, "start_fee")?)?,
+                        target_fee: int(field(data, "target_fee")?)?,
+                        start_time: uint(field(data, "start_time")?)?,
+                        end_time: uint(field(data, "end_time")?)?,
+                    },
+                    _ => return Ok(None),
+                }
+            }
+            // Governance changes the fee without a SWAP_FEE event
+            "fee_proposal_executed" => PoolEvent::FeeProposalExecuted {
+                id: uint(topic(event, 1)?)? as u32,
+                fee: int(data)?,
+            },
+            "mint" => PoolEvent::Mint {
+                to: Address::from_sc_val(topic(event, 2)?)?,
+                amount: int(data)?,
+            },
+            "transfer" => PoolEvent::Transfer {
+                from: Address::from_sc_val(topic(event, 1)?)?,
+                to: Address::from_sc_val(topic(event, 2)?)?,
+                amount: int(data)?,
+            },
+            "burn" => PoolEvent::Burn {
+                from: Address::from_sc_val(topic(event, 1)?)?,
+                amount: int(data)?,
+            },
+            "clawback" => PoolEvent::Clawback {
+                from: Address::from_sc_val(topic(event, 2)?)?,
+                amount: int(data)?,
+            },
+            _ => return Ok(None),
+        };
+        Ok(Some(decoded))
+    }
+
+    fn decode_swap(data: &ScVal) -> Result<Self, Error> {
+        Ok(PoolEvent::Swap {
+            caller: Address::from_sc_val(field(data, "caller")?)?,
+            token_in: Address::from_sc_val(field(data, "token_in")?)?,
+            token_out: Address::from_sc_val(field(data, "token_out")?)?,
+            token_amount_in: int(field(data, "token_amount_in")?)?,
+            token_amount_out: int(field(data, "token_amount_out")?)?,
+            referral_amount: field(data, "referral_amount").and_then(int).ok(),
+        })
+    }
+
+    fn decode_join(data: &ScVal) -> Result<Self, Error> {
+        Ok(PoolEvent::Join {
+            caller: Address::from_sc_val(field(data, "caller")?)?,
+            token_in: Address::from_sc_val(field(data, "token_in")?)?,
+            token_amount_in: int(field(data, "token_amount_in")?)?,
+            pool_amount_out: field(data, "pool_amount_out").and_then(int).ok(),
+        })
+    }
+
+    fn decode_exit(data: &ScVal) -> Result<Self, Error> {
+        Ok(PoolEvent::Exit {
+            caller: Address::from_sc_val(field(data, "caller")?)?,
+            token_out: Address::from_sc_val(field(data, "token_out")?)?,
+            token_amount_out: int(field(data, "token_amount_out")?)?,
+            pool_amount_in: field(data, "pool_amount_in").and_then(int).ok(),
+        })
+    }
+}
+
+fn symbol(val: &ScVal) -> Option<String> {
+    match val {
+        ScVal::Symbol(sym) => std::str::from_utf8(sym.0.as_slice()).ok().map(String::from),
+        _ => None,
+    }
+}
+
+fn topic(event: &RawEvent, i: usize) -> Result<&ScVal, Error> {
+    event
+        .topics
+        .get(i)
+        .ok_or_else(|| Error::Decode(format!("missing topic {}", i)))
+}
+
+fn int(val: &ScVal) -> Result<i128, Error> {
+    match val {
+        ScVal::I128(parts) => Ok(((parts.hi as i128) << 64) | parts.lo as i128),
+        _ => Err(Error::Decode(format!("expected an i128, got {:?}", val))),
+    }
+}
+
+fn uint(val: &ScVal) -> Result<u64, Error> {
+    match val {
+        ScVal::U32(n) => Ok(u64::from(*n)),
+        ScVal::U64(n) => Ok(*n),
+        _ => Err(Error::Decode(format!(
+            "expected an unsigned int, got {:?}",
+            val
+        ))),
+    }
+}
+
+fn as_vec(val: &ScVal) -> Result<&[ScVal], Error> {
+    match val {
+        ScVal::Vec(Some(vec)) => Ok(vec.0.as_slice()),
+        _ => Err(Error::Decode(format!("expected a vec, got {:?}", val))),
+    }
+}
+
+// Contract types are published as maps keyed by their field names
+fn field<'a>(val: &'a ScVal, name: &str) -> Result<&'a ScVal, Error> {
+    let entries = match val {
+        ScVal::Map(Some(map)) => map.0.as_slice(),
+        _ => return Err(Error::Decode(format!("expected a map, got {:?}", val))),
+    };
+    entries
+        .iter()
+        .find(|entry| symbol(&entry.key).as_deref() == Some(name))
+        .map(|entry| &entry.val)
+        .ok_or_else(|| Error::Decode(format!("missing field {}", name)))
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex(input: &str) -> Option<[u8; 32]> {
+    if input.len() != 64 || !input.is_ascii() {
+        return None;
+    }
+    let mut out = [0u8; 32];
+    for (i, byte) in out.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&input[2 * i..2 * i + 2], 16).ok()?;
+    }
+    Some(out)
+}
@@ -0,0 +1,12 @@
+// Rebuilds a Comet pool's records, total shares and LP balances from the events it publishes,
+// along with the swap volumes, fees and prices an indexer needs.
+
+mod error;
+mod event;
+mod math;
+mod pool;
+mod tests;
+
+pub use error::Error;
+pub use event::{Address, PoolEvent, RawEvent};
+pub use pool::{FeeRamp, Pool, Record, TokenStats};
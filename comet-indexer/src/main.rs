@@ -0,0 +1,80 @@
+// comet-indexer <events file> <pool contract id, hex>
+//
+// Each line of the file is one event, either base64 XDR of a ContractEvent or a soroban-rpc
+// getEvents entry in JSON. Prints the pool state rebuilt from them.
+
+use std::{env, fs, process};
+
+use comet_indexer::{Address, Pool, RawEvent};
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() != 3 {
+        eprintln!("usage: comet-indexer <events file> <pool contract id>");
+        process::exit(2);
+    }
+    let contract_id = match Address::contract_from_hex(&args[2]) {
+        Some(Address::Contract(id)) => id,
+        _ => fail(format!("{} isn't a hex contract id", args[2])),
+    };
+    let input = fs::read_to_string(&args[1])
+        .unwrap_or_else(|e| fail(format!("can't read {}: {}", args[1], e)));
+
+    let mut pool = Pool::new(contract_id);
+    for (i, line) in input.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let event =
+            RawEvent::parse(line).unwrap_or_else(|e| fail(format!("line {}: {}", i + 1, e)));
+        pool.ingest(&event)
+            .unwrap_or_else(|e| fail(format!("line {}: {}", i + 1, e)));
+    }
+    print_pool(&pool);
+}
+
+fn print_pool(pool: &Pool) {
+    println!("pool {}", pool.address);
+    println!("finalized {}", pool.finalized);
+    println!("swap fee {}", pool.swap_fee);
+    if let Some(ramp) = &pool.fee_ramp {
+        println!(
+            "fee ramp {} to {} from {} to {}",
+            ramp.start_fee, ramp.target_fee, ramp.start_time, ramp.end_time
+        );
+    }
+    println!("total shares {}", pool.total_shares);
+    println!("total weight {}", pool.total_weight());
+
+    // Prices and value are quoted in the first bound token
+    let numeraire = pool.tokens.first();
+    for token in &pool.tokens {
+        let record = &pool.records[token];
+        let stats = pool.stats.get(token).cloned().unwrap_or_default();
+        let price = numeraire.and_then(|n| pool.spot_price_sans_fee(n, token));
+        println!(
+            "token {} balance {} denorm {} price {} volume in {} volume out {} fees {} swaps {}",
+            token,
+            record.balance,
+            record.denorm,
+            price.map_or("-".to_string(), |p| p.to_string()),
+            stats.volume_in,
+            stats.volume_out,
+            stats.fees,
+            stats.swaps,
+        );
+    }
+    if let Some(value) = numeraire.and_then(|n| pool.value_locked(n)) {
+        println!("value locked {}", value);
+    }
+    for (holder, balance) in &pool.lp_balances {
+        if *balance != 0 {
+            println!("lp {} {}", holder, balance);
+        }
+    }
+}
+
+fn fail(msg: String) -> ! {
+    eprintln!("comet-indexer: {}", msg);
+    process::exit(1);
+}
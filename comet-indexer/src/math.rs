@@ -0,0 +1,52 @@
+// Fixed point helpers matching the pool's c_num and c_math, so prices come out to the stroop.
+// Recovering swap fees needs a fractional power, that one is estimated in f64.
+
+pub const BONE: i128 = 1e7 as i128;
+
+pub fn c_mul(a: i128, b: i128) -> Option<i128> {
+    a.checked_mul(b)?.checked_add(BONE / 2).map(|c| c / BONE)
+}
+
+pub fn c_div(a: i128, b: i128) -> Option<i128> {
+    if b == 0 {
+        return None;
+    }
+    a.checked_mul(BONE)?.checked_add(b / 2).map(|c| c / b)
+}
+
+// Units of token in per unit of token out, as the pool's calc_spot_price
+pub fn calc_spot_price(
+    token_balance_in: i128,
+    token_weight_in: i128,
+    token_balance_out: i128,
+    token_weight_out: i128,
+    swap_fee: i128,
+) -> Option<i128> {
+    let numer = c_div(token_balance_in, token_weight_in)?;
+    let denom = c_div(token_balance_out, token_weight_out)?;
+    let ratio = c_div(numer, denom)?;
+    let scale = c_div(BONE, BONE - swap_fee)?;
+    c_mul(ratio, scale)
+}
+
+// Part of `token_amount_in` a swap kept as its fee, found by inverting calc_token_out_given_token_in
+// on the balances before the swap. The pool's fee depends on hooks, fee multipliers and ramps
+// that the events don't carry, so it's recovered from the trade itself. Worked out in f64, the
+// result can be a few stroops off on large balances.
+pub fn implied_swap_fee(
+    token_balance_in: i128,
+    token_weight_in: i128,
+    token_balance_out: i128,
+    token_weight_out: i128,
+    token_amount_in: i128,
+    token_amount_out: i128,
+) -> i128 {
+    if token_balance_out <= token_amount_out || token_weight_in == 0 {
+        return 0;
+    }
+    let remaining = 1.0 - token_amount_out as f64 / token_balance_out as f64;
+    let weight_ratio = token_weight_out as f64 / token_weight_in as f64;
+    let adjusted_in = token_balance_in as f64 * (remaining.powf(-weight_ratio) - 1.0);
+    let fee = (token_amount_in as f64 - adjusted_in).round() as i128;
+    fee.clamp(0, token_amount_in)
+}
@@ -0,0 +1,268 @@
+use std::collections::BTreeMap;
+
+use crate::{
+    error::Error,
+    event::{Address, PoolEvent, RawEvent},
+    math::{c_mul, calc_spot_price, implied_swap_fee},
+};
+
+// Swap fee a pool starts with, the contract's MIN_FEE
+const INIT_SWAP_FEE: i128 = 10;
+
+// Same fields as the contract's Record
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Record {
+    pub bound: bool,
+    pub index: u32,
+    pub denorm: i128,
+    pub balance: i128,
+}
+
+// Same fields as the contract's FeeRamp
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FeeRamp {
+    pub start_fee: i128,
+    pub target_fee: i128,
+    pub start_time: u64,
+    pub end_time: u64,
+}
+
+// Swap totals of one token since the first replayed event
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TokenStats {
+    pub volume_in: i128,
+    pub volume_out: i128,
+    pub fees: i128, // swap fees kept by the pool, paid in this token
+    pub swaps: u64, // swaps that took this token in
+}
+
+#[derive(Clone, Debug)]
+pub struct Pool {
+    pub address: Address,
+    pub tokens: Vec<Address>,
+    pub records: BTreeMap<Address, Record>,
+    pub total_shares: i128,
+    pub lp_balances: BTreeMap<Address, i128>,
+    pub swap_fee: i128, // stored swap fee, the target while a ramp runs, see swap_fee_at
+    pub fee_ramp: Option<FeeRamp>,
+    pub finalized: bool,
+    pub stats: BTreeMap<Address, TokenStats>,
+}
+
+impl Pool {
+    pub fn new(contract_id: [u8; 32]) -> Self {
+        Pool {
+            address: Address::Contract(contract_id),
+            tokens: Vec::new(),
+            records: BTreeMap::new(),
+            total_shares: 0,
+            lp_balances: BTreeMap::new(),
+            swap_fee: INIT_SWAP_FEE,
+            fee_ramp: None,
+            finalized: false,
+            stats: BTreeMap::new(),
+        }
+    }
+
+    // Replays `events` in order, skipping those published by other contracts
+    pub fn from_events<'a>(
+        contract_id: [u8; 32],
+        events: impl IntoIterator<Item = &'a RawEvent>,
+    ) -> Result<Self, Error> {
+        let mut pool = Pool::new(contract_id);
+        for event in events {
+            pool.ingest(event)?;
+        }
+        Ok(pool)
+    }
+
+    pub fn ingest(&mut self, event: &RawEvent) -> Result<(), Error> {
+        if event.contract_id.map(Address::Contract) != Some(self.address) {
+            return Ok(());
+        }
+        if let Some(decoded) = PoolEvent::decode(event)? {
+            self.apply(&decoded);
+        }
+        Ok(())
+    }
+
+    pub fn apply(&mut self, event: &PoolEvent) {
+        match event {
+            PoolEvent::Bind {
+                token,
+                balance,
+                denorm,
+            } => {
+                let record = Record {
+                    bound: true,
+                    index: self.tokens.len() as u32,
+                    denorm: *denorm,
+                    balance: *balance,
+                };
+                self.tokens.push(*token);
+                self.records.insert(*token, record);
+            }
+            PoolEvent::Rebind {
+                token,
+                balance,
+                denorm,
+            } => {
+                let record = self.records.entry(*token).or_default();
+                record.balance = *balance;
+                record.denorm = *denorm;
+            }
+            PoolEvent::Finalize { .. } => self.finalized = true,
+            // A new fee cancels any ramp, as in the pool
+            PoolEvent::SwapFee { fee } | PoolEvent::FeeProposalExecuted { fee, .. } => {
+                self.swap_fee = *fee;
+                self.fee_ramp = None;
+            }
+            PoolEvent::FeeRamp {
+                start_fee,
+                target_fee,
+                start_time,
+                end_time,
+            } => {
+                self.swap_fee = *target_fee;
+                self.fee_ramp = Some(FeeRamp {
+                    start_fee: *start_fee,
+                    target_fee: *target_fee,
+                    start_time: *start_time,
+                    end_time: *end_time,
+                });
+            }
+            PoolEvent::Swap {
+                token_in,
+                token_out,
+                token_amount_in,
+                token_amount_out,
+                ..
+            } => {
+                let record_in = self.records.get(token_in).cloned().unwrap_or_default();
+                let record_out = self.records.get(token_out).cloned().unwrap_or_default();
+                let fee = implied_swap_fee(
+                    record_in.balance,
+                    record_in.denorm,
+                    record_out.balance,
+                    record_out.denorm,
+                    *token_amount_in,
+                    *token_amount_out,
+                );
+                self.add_balance(token_in, *token_amount_in);
+                self.add_balance(token_out, -token_amount_out);
+
+                let stats_in = self.stats.entry(*token_in).or_default();
+                stats_in.volume_in += token_amount_in;
+                stats_in.fees += fee;
+                stats_in.swaps += 1;
+                self.stats.entry(*token_out).or_default().volume_out += token_amount_out;
+            }
+            PoolEvent::Join {
+                token_in,
+                token_amount_in,
+                ..
+            } => self.add_balance(token_in, *token_amount_in),
+            PoolEvent::Exit {
+                token_out,
+                token_amount_out,
+                ..
+            } => self.add_balance(token_out, -token_amount_out),
+            // Picks the stream up from wherever the snapshot was taken, dropping unbound tokens
+            PoolEvent::Snapshot {
+                tokens,
+                balances,
+                weights,
+                total_shares,
+            } => {
+                self.records.retain(|token, _| tokens.contains(token));
+                for (i, token) in tokens.iter().enumerate() {
+                    let record = self.records.entry(*token).or_default();
+                    record.bound = true;
+                    record.index = i as u32;
+                    record.balance = balances.get(i).copied().unwrap_or_default();
+                    record.denorm = weights.get(i).copied().unwrap_or_default();
+                }
+                self.tokens = tokens.clone();
+                self.total_shares = *total_shares;
+            }
+            // Only the pool mints its shares
+            PoolEvent::Mint { to, amount } => {
+                *self.lp_balances.entry(*to).or_default() += amount;
+                self.total_shares += amount;
+            }
+            PoolEvent::Transfer { from, to, amount } => {
+                *self.lp_balances.entry(*from).or_default() -= amount;
+                *self.lp_balances.entry(*to).or_default() += amount;
+            }
+            // The supply only shrinks when the pool burns shares it pulled in on an exit
+            PoolEvent::Burn { from, amount } => {
+                *self.lp_balances.entry(*from).or_default() -= amount;
+                if *from == self.address {
+                    self.total_shares -= amount;
+                }
+            }
+            PoolEvent::Clawback { from, amount } => {
+                *self.lp_balances.entry(*from).or_default() -= amount;
+            }
+        }
+    }
+
+    fn add_balance(&mut self, token: &Address, amount: i128) {
+        self.records.entry(*token).or_default().balance += amount;
+    }
+
+    pub fn balance(&self, token: &Address) -> Option<i128> {
+        self.records.get(token).map(|record| record.balance)
+    }
+
+    pub fn lp_balance(&self, holder: &Address) -> i128 {
+        self.lp_balances.get(holder).copied().unwrap_or(0)
+    }
+
+    // Swap fee at a ledger timestamp, moved along the ramp like the pool's read_swap_fee
+    pub fn swap_fee_at(&self, timestamp: u64) -> i128 {
+        match &self.fee_ramp {
+            Some(ramp) if timestamp < ramp.end_time => {
+                let elapsed = i128::from(timestamp.saturating_sub(ramp.start_time));
+                let duration = i128::from(ramp.end_time - ramp.start_time);
+                ramp.start_fee + (ramp.target_fee - ramp.start_fee) * elapsed / duration
+            }
+            _ => self.swap_fee,
+        }
+    }
+
+    pub fn total_weight(&self) -> i128 {
+        self.records.values().map(|record| record.denorm).sum()
+    }
+
+    // Units of `token_in` per unit of `token_out`, as get_spot_price once any fee ramp is over
+    pub fn spot_price(&self, token_in: &Address, token_out: &Address) -> Option<i128> {
+        self.price(token_in, token_out, self.swap_fee)
+    }
+
+    pub fn spot_price_sans_fee(&self, token_in: &Address, token_out: &Address) -> Option<i128> {
+        self.price(token_in, token_out, 0)
+    }
+
+    fn price(&self, token_in: &Address, token_out: &Address, swap_fee: i128) -> Option<i128> {
+        let record_in = self.records.get(token_in)?;
+        let record_out = self.records.get(token_out)?;
+        calc_spot_price(
+            record_in.balance,
+            record_in.denorm,
+            record_out.balance,
+            record_out.denorm,
+            swap_fee,
+        )
+    }
+
+    // Every balance priced in `numeraire` at the current spot prices
+    pub fn value_locked(&self, numeraire: &Address) -> Option<i128> {
+        let mut value = 0i128;
+        for token in &self.tokens {
+            let price = self.spot_price_sans_fee(numeraire, token)?;
+            value = value.checked_add(c_mul(self.balance(token)?, price)?)?;
+        }
+        Some(value)
+    }
+}
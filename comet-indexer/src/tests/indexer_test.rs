@@ -0,0 +1,266 @@
+#![cfg(test)]
+
+use crate::math::BONE;
+use crate::{Address, FeeRamp, Pool, PoolEvent, RawEvent};
+use contracts::testutils::create_pool;
+use contracts::GOV_EXECUTION_DELAY;
+use soroban_sdk::testutils::{Address as _, Events, Ledger};
+use soroban_sdk::xdr::{ScVal, WriteXdr};
+use soroban_sdk::{vec, Env, IntoVal, RawVal, TryFromVal};
+
+fn to_stroop<T: Into<f64>>(a: T) -> i128 {
+    (a.into() * 1e7) as i128
+}
+
+fn to_indexer_address(e: &Env, address: &soroban_sdk::Address) -> Address {
+    let raw: RawVal = address.into_val(e);
+    Address::from_sc_val(&ScVal::try_from_val(e, &raw).unwrap()).unwrap()
+}
+
+// Everything the test env recorded, as the indexer would read it off the ledger
+fn captured_events(e: &Env) -> Vec<RawEvent> {
+    let mut events = Vec::new();
+    for event in e.events().all().iter() {
+        let (contract_id, topics, data) = event.unwrap();
+        events.push(RawEvent {
+            contract_id: Some(contract_id.to_array()),
+            topics: topics
+                .iter()
+                .map(|topic| ScVal::try_from_val(e, &topic.unwrap()).unwrap())
+                .collect(),
+            data: ScVal::try_from_val(e, &data).unwrap(),
+        });
+    }
+    events
+}
+
+// Same event in the shape soroban-rpc's getEvents returns
+fn to_rpc_json(event: &RawEvent) -> String {
+    let encode = |val: &ScVal| base64::encode(val.to_xdr().unwrap());
+    let contract_id: String = event
+        .contract_id
+        .unwrap()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    let topics: Vec<String> = event
+        .topics
+        .iter()
+        .map(|topic| format!("\"{}\"", encode(topic)))
+        .collect();
+    format!(
+        "{{\"type\":\"contract\",\"contractId\":\"{}\",\"topic\":[{}],\"value\":{{\"xdr\":\"{}\"}}}}",
+        contract_id,
+        topics.join(","),
+        encode(&event.data)
+    )
+}
+
+#[test]
+fn test_rebuild_pool_state() {
+    let env = Env::default();
+    let admin = soroban_sdk::Address::random(&env);
+    let user = soroban_sdk::Address::random(&env);
+    let holder = soroban_sdk::Address::random(&env);
+    let (client, tokens) = create_pool(&env, &admin);
+    let contract_address = soroban_sdk::Address::from_contract_id(&env, &client.contract_id);
+    for (token, amount) in tokens.iter().zip([20, 10, 5000]) {
+        token.mint(&admin, &user, &to_stroop(amount));
+        token.incr_allow(&user, &contract_address, &i128::MAX);
+    }
+
+    client.swap_exact_amount_in(
+        &tokens[0].address(),
+        &to_stroop(2),
+        &tokens[1].address(),
+        &0,
+        &i128::MAX,
+        &user,
+        &None,
+        &0,
+    );
+    client.dep_tokn_amt_in_get_lp_tokns_out(&tokens[2].address(), &to_stroop(100), &0, &user);
+    client.join_pool(
+        &to_stroop(5),
+        &vec![&env, i128::MAX, i128::MAX, i128::MAX],
+        &user,
+    );
+    client.xfer(&admin, &holder, &to_stroop(10));
+
+    // The rest goes out in the legacy format, without the LP amounts on joins and exits
+    client.set_legacy_events(&admin, &true);
+    client.exit_pool(&to_stroop(3), &vec![&env, 0, 0, 0], &user);
+    client.wdr_tokn_amt_in_get_lp_tokns_out(&tokens[1].address(), &to_stroop(1), &0, &holder);
+    client.swap_exact_amount_in(
+        &tokens[2].address(),
+        &to_stroop(50),
+        &tokens[0].address(),
+        &0,
+        &i128::MAX,
+        &user,
+        &None,
+        &0,
+    );
+
+    // Round trip every event through both input formats
+    let events = captured_events(&env);
+    for event in &events {
+        assert_eq!(
+            &RawEvent::parse(&event.to_xdr_base64().unwrap()).unwrap(),
+            event
+        );
+        assert_eq!(&RawEvent::parse(&to_rpc_json(event)).unwrap(), event);
+    }
+    let pool = Pool::from_events(client.contract_id.to_array(), &events).unwrap();
+
+    // Legacy swaps don't carry the referral amount
+    let referral_amounts: Vec<Option<i128>> = events
+        .iter()
+        .filter(|event| event.contract_id == Some(client.contract_id.to_array()))
+        .filter_map(|event| match PoolEvent::decode(event).unwrap() {
+            Some(PoolEvent::Swap {
+                referral_amount, ..
+            }) => Some(referral_amount),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(referral_amounts, [Some(0), None]);
+
+    let current_tokens: Vec<Address> = client
+        .get_current_tokens()
+        .iter()
+        .map(|token| to_indexer_address(&env, &token.unwrap()))
+        .collect();
+    assert_eq!(pool.tokens, current_tokens);
+    assert!(pool.finalized);
+    assert_eq!(pool.swap_fee, client.get_swap_fee());
+    assert_eq!(pool.total_weight(), client.get_total_denormalized_weight());
+    for token in &tokens {
+        let address = to_indexer_address(&env, &token.address());
+        let record = &pool.records[&address];
+        assert_eq!(record.balance, client.get_balance(&token.address()));
+        assert_eq!(
+            record.denorm,
+            client.get_denormalized_weight(&token.address())
+        );
+    }
+    for (token_in, token_out) in [(0, 1), (1, 2), (2, 0)] {
+        let (token_in, token_out) = (&tokens[token_in], &tokens[token_out]);
+        let (address_in, address_out) = (
+            to_indexer_address(&env, &token_in.address()),
+            to_indexer_address(&env, &token_out.address()),
+        );
+        assert_eq!(
+            pool.spot_price(&address_in, &address_out),
+            Some(client.get_spot_price(&token_in.address(), &token_out.address()))
+        );
+        assert_eq!(
+            pool.spot_price_sans_fee(&address_in, &address_out),
+            Some(client.get_spot_price_sans_fee(&token_in.address(), &token_out.address()))
+        );
+    }
+
+    let mut total_shares = 0;
+    for lp in [&admin, &user, &holder, &contract_address] {
+        let balance = client.balance(lp);
+        assert_eq!(pool.lp_balance(&to_indexer_address(&env, lp)), balance);
+        total_shares += balance;
+    }
+    assert_eq!(pool.total_shares, total_shares);
+
+    // 0.3% of each swap stays in the pool, recovered to within the pool's rounding
+    let stats = &pool.stats[&to_indexer_address(&env, &tokens[0].address())];
+    assert_eq!(stats.volume_in, to_stroop(2));
+    assert_eq!(stats.swaps, 1);
+    assert!((stats.fees - to_stroop(0.006)).abs() * 50 <= to_stroop(0.006));
+    let stats = &pool.stats[&to_indexer_address(&env, &tokens[2].address())];
+    assert_eq!(stats.volume_in, to_stroop(50));
+    assert!((stats.fees - to_stroop(0.15)).abs() * 50 <= to_stroop(0.15));
+}
+
+#[test]
+fn test_snapshot_resumes_stream() {
+    let env = Env::default();
+    let admin = soroban_sdk::Address::random(&env);
+    let (client, tokens) = create_pool(&env, &admin);
+    let events = captured_events(&env);
+
+    // Starting from the finalize snapshot alone gives back the balances and supply
+    let snapshot = events
+        .iter()
+        .filter(|event| event.contract_id == Some(client.contract_id.to_array()))
+        .filter_map(|event| PoolEvent::decode(event).unwrap())
+        .rfind(|event| matches!(event, PoolEvent::Snapshot { .. }))
+        .unwrap();
+    let mut pool = Pool::new(client.contract_id.to_array());
+    // A token the snapshot no longer lists is dropped
+    let stale = to_indexer_address(&env, &soroban_sdk::Address::random(&env));
+    pool.apply(&PoolEvent::Bind {
+        token: stale,
+        balance: to_stroop(1),
+        denorm: to_stroop(1),
+    });
+    pool.apply(&snapshot);
+    assert_eq!(pool.records.get(&stale), None);
+    assert_eq!(pool.tokens.len(), tokens.len());
+    assert_eq!(pool.total_shares, 100 * BONE);
+    assert_eq!(pool.total_weight(), client.get_total_denormalized_weight());
+    for token in &tokens {
+        assert_eq!(
+            pool.balance(&to_indexer_address(&env, &token.address())),
+            Some(client.get_balance(&token.address()))
+        );
+    }
+
+    // The weights come with the snapshot, so prices do too
+    let numeraire = &tokens[0];
+    for token in &tokens[1..] {
+        assert_eq!(
+            pool.spot_price_sans_fee(
+                &to_indexer_address(&env, &numeraire.address()),
+                &to_indexer_address(&env, &token.address())
+            ),
+            Some(client.get_spot_price_sans_fee(&numeraire.address(), &token.address()))
+        );
+    }
+}
+
+#[test]
+fn test_swap_fee_follows_governance_and_ramps() {
+    let env = Env::default();
+    env.ledger().with_mut(|li| {
+        li.sequence_number = 100;
+        li.timestamp = 1000;
+    });
+    let admin = soroban_sdk::Address::random(&env);
+    let (client, _) = create_pool(&env, &admin);
+    client.delegate(&admin, &admin);
+
+    env.ledger().with_mut(|li| li.sequence_number = 101);
+    let proposal_id = client.propose_swap_fee(&admin, &to_stroop(0.005));
+    client.vote_swap_fee(&admin, &proposal_id, &true);
+    let proposal = client.get_fee_proposal(&proposal_id);
+    env.ledger()
+        .with_mut(|li| li.sequence_number = proposal.end_ledger + GOV_EXECUTION_DELAY + 1);
+    client.execute_swap_fee(&proposal_id);
+
+    let pool = Pool::from_events(client.contract_id.to_array(), &captured_events(&env)).unwrap();
+    assert_eq!(pool.swap_fee, to_stroop(0.005));
+    assert_eq!(pool.fee_ramp, None);
+
+    client.ramp_swap_fee(&admin, &to_stroop(0.009), &1100);
+    env.ledger().with_mut(|li| li.timestamp = 1040);
+    let pool = Pool::from_events(client.contract_id.to_array(), &captured_events(&env)).unwrap();
+    assert_eq!(
+        pool.fee_ramp,
+        Some(FeeRamp {
+            start_fee: to_stroop(0.005),
+            target_fee: to_stroop(0.009),
+            start_time: 1000,
+            end_time: 1100,
+        })
+    );
+    assert_eq!(pool.swap_fee, to_stroop(0.009));
+    assert_eq!(pool.swap_fee_at(1040), client.get_swap_fee());
+    assert_eq!(pool.swap_fee_at(1100), to_stroop(0.009));
+}
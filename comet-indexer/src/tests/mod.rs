@@ -0,0 +1 @@
+pub mod indexer_test;
@@ -0,0 +1,2 @@
+pub const ACC_PRECISION: i128 = 1e12 as i128; // scale of the reward per share accumulators
+pub const MAX_REWARD_TOKENS: u32 = 8; // most reward tokens a staking contract can emit
@@ -0,0 +1,278 @@
+use soroban_sdk::{contractimpl, vec, Address, BytesN, Env, Vec};
+
+use crate::{
+    consts::{ACC_PRECISION, MAX_REWARD_TOKENS},
+    events::{
+        add_reward_event, claim_event, deposit_event, emergency_withdraw_event, set_rate_event,
+        withdraw_event,
+    },
+    metadata::{
+        read_admin, read_lp_token, read_rewards, read_staker, read_total_staked, write_admin,
+        write_lp_token, write_rewards, write_staker, write_total_staked,
+    },
+    storage_types::{DataKey, RewardInfo, StakerInfo},
+};
+
+// Token Interface
+mod token {
+    soroban_sdk::contractimport!(file = "../soroban_token_spec.wasm");
+}
+
+pub struct CometRewardsContract;
+
+pub trait RewardsTrait {
+    fn init(e: Env, admin: Address, lp_token: BytesN<32>);
+
+    fn add_reward(e: Env, admin: Address, token: Address, rate: i128);
+
+    fn set_reward_rate(e: Env, admin: Address, token: Address, rate: i128);
+
+    fn deposit(e: Env, user: Address, amount: i128);
+
+    fn withdraw(e: Env, user: Address, amount: i128);
+
+    fn claim(e: Env, user: Address) -> Vec<i128>;
+
+    fn emergency_withdraw(e: Env, user: Address) -> i128;
+
+    fn get_admin(e: Env) -> Address;
+
+    fn get_lp_token(e: Env) -> BytesN<32>;
+
+    fn get_rewards(e: Env) -> Vec<RewardInfo>;
+
+    fn get_staked(e: Env, user: Address) -> i128;
+
+    fn get_total_staked(e: Env) -> i128;
+
+    fn get_pending(e: Env, user: Address) -> Vec<i128>;
+}
+
+#[contractimpl]
+impl RewardsTrait for CometRewardsContract {
+    fn init(e: Env, admin: Address, lp_token: BytesN<32>) {
+        assert!(!e.storage().has(&DataKey::Admin), "already initialized");
+
+        write_admin(&e, admin);
+        write_lp_token(&e, lp_token);
+        write_total_staked(&e, 0);
+    }
+
+    fn add_reward(e: Env, admin: Address, token: Address, rate: i128) {
+        assert!(admin == read_admin(&e), "ERR_NOT_ADMIN");
+        admin.require_auth();
+        assert!(rate >= 0, "ERR_NEGATIVE_RATE");
+
+        let mut rewards = update_rewards(&e);
+        assert!(rewards.len() < MAX_REWARD_TOKENS, "ERR_MAX_REWARDS");
+        assert!(find_reward(&rewards, &token).is_none(), "ERR_REWARD_EXISTS");
+        rewards.push_back(RewardInfo {
+            token: token.clone(),
+            rate,
+            acc_per_share: 0,
+            last_update: e.ledger().timestamp(),
+        });
+        write_rewards(&e, rewards);
+        add_reward_event(&e, admin, token, rate);
+    }
+
+    fn set_reward_rate(e: Env, admin: Address, token: Address, rate: i128) {
+        assert!(admin == read_admin(&e), "ERR_NOT_ADMIN");
+        admin.require_auth();
+        assert!(rate >= 0, "ERR_NEGATIVE_RATE");
+
+        // Everything emitted so far is accounted for at the old rate
+        let mut rewards = update_rewards(&e);
+        let index = find_reward(&rewards, &token).expect("ERR_NO_REWARD");
+        let mut reward = rewards.get(index).unwrap().unwrap();
+        reward.rate = rate;
+        rewards.set(index, reward);
+        write_rewards(&e, rewards);
+        set_rate_event(&e, admin, token, rate);
+    }
+
+    fn deposit(e: Env, user: Address, amount: i128) {
+        user.require_auth();
+        assert!(amount > 0, "ERR_ZERO_AMOUNT");
+
+        let rewards = update_rewards(&e);
+        let mut staker = settle_staker(&e, &rewards, read_staker(&e, user.clone()));
+        staker.amount += amount;
+        staker.reward_debt = reward_debt(&e, &rewards, staker.amount);
+        write_staker(&e, user.clone(), staker);
+        write_total_staked(&e, read_total_staked(&e) + amount);
+
+        token::Client::new(&e, &read_lp_token(&e)).xfer_from(
+            &e.current_contract_address(),
+            &user,
+            &e.current_contract_address(),
+            &amount,
+        );
+        deposit_event(&e, user, amount);
+    }
+
+    fn withdraw(e: Env, user: Address, amount: i128) {
+        user.require_auth();
+        assert!(amount > 0, "ERR_ZERO_AMOUNT");
+
+        let rewards = update_rewards(&e);
+        let mut staker = settle_staker(&e, &rewards, read_staker(&e, user.clone()));
+        assert!(staker.amount >= amount, "ERR_INSUFFICIENT_STAKE");
+        staker.amount -= amount;
+        staker.reward_debt = reward_debt(&e, &rewards, staker.amount);
+        write_staker(&e, user.clone(), staker);
+        write_total_staked(&e, read_total_staked(&e) - amount);
+
+        token::Client::new(&e, &read_lp_token(&e)).xfer(
+            &e.current_contract_address(),
+            &user,
+            &amount,
+        );
+        withdraw_event(&e, user, amount);
+    }
+
+    fn claim(e: Env, user: Address) -> Vec<i128> {
+        user.require_auth();
+
+        let rewards = update_rewards(&e);
+        let mut staker = settle_staker(&e, &rewards, read_staker(&e, user.clone()));
+        let claimed = staker.pending.clone();
+        staker.pending = zeros(&e, rewards.len());
+        write_staker(&e, user.clone(), staker);
+
+        for i in 0..rewards.len() {
+            let amount = claimed.get(i).unwrap().unwrap();
+            if amount > 0 {
+                let reward = rewards.get(i).unwrap().unwrap();
+                token::Client::new(&e, &reward.token.contract_id().unwrap()).xfer(
+                    &e.current_contract_address(),
+                    &user,
+                    &amount,
+                );
+            }
+        }
+        claim_event(&e, user, claimed.clone());
+        claimed
+    }
+
+    fn emergency_withdraw(e: Env, user: Address) -> i128 {
+        user.require_auth();
+
+        // Skips the reward accounting entirely, anything pending is forfeited
+        let staker = read_staker(&e, user.clone());
+        let amount = staker.amount;
+        write_staker(
+            &e,
+            user.clone(),
+            StakerInfo {
+                amount: 0,
+                reward_debt: vec![&e],
+                pending: vec![&e],
+            },
+        );
+        write_total_staked(&e, read_total_staked(&e) - amount);
+
+        if amount > 0 {
+            token::Client::new(&e, &read_lp_token(&e)).xfer(
+                &e.current_contract_address(),
+                &user,
+                &amount,
+            );
+        }
+        emergency_withdraw_event(&e, user, amount);
+        amount
+    }
+
+    fn get_admin(e: Env) -> Address {
+        read_admin(&e)
+    }
+
+    fn get_lp_token(e: Env) -> BytesN<32> {
+        read_lp_token(&e)
+    }
+
+    fn get_rewards(e: Env) -> Vec<RewardInfo> {
+        accrue_rewards(&e)
+    }
+
+    fn get_staked(e: Env, user: Address) -> i128 {
+        read_staker(&e, user).amount
+    }
+
+    fn get_total_staked(e: Env) -> i128 {
+        read_total_staked(&e)
+    }
+
+    fn get_pending(e: Env, user: Address) -> Vec<i128> {
+        let rewards = accrue_rewards(&e);
+        settle_staker(&e, &rewards, read_staker(&e, user)).pending
+    }
+}
+
+// Rewards with their accumulators brought up to the current ledger timestamp, without saving them
+fn accrue_rewards(e: &Env) -> Vec<RewardInfo> {
+    let now = e.ledger().timestamp();
+    let total_staked = read_total_staked(e);
+    let mut rewards = read_rewards(e);
+    for i in 0..rewards.len() {
+        let mut reward = rewards.get(i).unwrap().unwrap();
+        if total_staked > 0 && now > reward.last_update {
+            let emitted = reward
+                .rate
+                .checked_mul(i128::from(now - reward.last_update))
+                .expect("ERR_EMISSION_OVERFLOW");
+            reward.acc_per_share += emitted
+                .checked_mul(ACC_PRECISION)
+                .expect("ERR_EMISSION_OVERFLOW")
+                / total_staked;
+        }
+        reward.last_update = now;
+        rewards.set(i, reward);
+    }
+    rewards
+}
+
+fn update_rewards(e: &Env) -> Vec<RewardInfo> {
+    let rewards = accrue_rewards(e);
+    write_rewards(e, rewards.clone());
+    rewards
+}
+
+// Moves what the staker earned since its last update into `pending`
+fn settle_staker(e: &Env, rewards: &Vec<RewardInfo>, staker: StakerInfo) -> StakerInfo {
+    let mut pending: Vec<i128> = vec![e];
+    for i in 0..rewards.len() {
+        let reward = rewards.get(i).unwrap().unwrap();
+        // Rewards added after the staker's last update have no debt or pending yet
+        let debt = staker.reward_debt.get(i).map_or(0, |debt| debt.unwrap());
+        let earned = staker.amount * reward.acc_per_share / ACC_PRECISION - debt;
+        let previous = staker.pending.get(i).map_or(0, |pending| pending.unwrap());
+        pending.push_back(previous + earned);
+    }
+    StakerInfo {
+        amount: staker.amount,
+        reward_debt: reward_debt(e, rewards, staker.amount),
+        pending,
+    }
+}
+
+fn reward_debt(e: &Env, rewards: &Vec<RewardInfo>, amount: i128) -> Vec<i128> {
+    let mut debt: Vec<i128> = vec![e];
+    for i in 0..rewards.len() {
+        let reward = rewards.get(i).unwrap().unwrap();
+        debt.push_back(amount * reward.acc_per_share / ACC_PRECISION);
+    }
+    debt
+}
+
+fn find_reward(rewards: &Vec<RewardInfo>, token: &Address) -> Option<u32> {
+    (0..rewards.len()).find(|i| rewards.get(*i).unwrap().unwrap().token == *token)
+}
+
+fn zeros(e: &Env, len: u32) -> Vec<i128> {
+    let mut zeros: Vec<i128> = vec![e];
+    for _ in 0..len {
+        zeros.push_back(0);
+    }
+    zeros
+}
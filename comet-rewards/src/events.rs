@@ -0,0 +1,31 @@
+use soroban_sdk::{Address, Env, Symbol, Vec};
+
+pub fn add_reward_event(e: &Env, admin: Address, token: Address, rate: i128) {
+    let topics = (Symbol::new(e, "add_reward"), admin, token);
+    e.events().publish(topics, rate);
+}
+
+pub fn set_rate_event(e: &Env, admin: Address, token: Address, rate: i128) {
+    let topics = (Symbol::short("set_rate"), admin, token);
+    e.events().publish(topics, rate);
+}
+
+pub fn deposit_event(e: &Env, user: Address, amount: i128) {
+    let topics = (Symbol::short("deposit"), user);
+    e.events().publish(topics, amount);
+}
+
+pub fn withdraw_event(e: &Env, user: Address, amount: i128) {
+    let topics = (Symbol::short("withdraw"), user);
+    e.events().publish(topics, amount);
+}
+
+pub fn claim_event(e: &Env, user: Address, amounts: Vec<i128>) {
+    let topics = (Symbol::short("claim"), user);
+    e.events().publish(topics, amounts);
+}
+
+pub fn emergency_withdraw_event(e: &Env, user: Address, amount: i128) {
+    let topics = (Symbol::new(e, "emergency_withdraw"), user);
+    e.events().publish(topics, amount);
+}
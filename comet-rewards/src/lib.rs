@@ -0,0 +1,10 @@
+#![no_std]
+
+mod consts;
+mod contract;
+mod events;
+mod metadata;
+mod storage_types;
+mod tests;
+
+pub use contract::{CometRewardsContract, CometRewardsContractClient};
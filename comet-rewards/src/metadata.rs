@@ -0,0 +1,66 @@
+use soroban_sdk::{vec, Address, BytesN, Env, Vec};
+
+use crate::storage_types::{DataKey, RewardInfo, StakerInfo};
+
+// Read Admin
+pub fn read_admin(e: &Env) -> Address {
+    e.storage().get_unchecked(&DataKey::Admin).unwrap()
+}
+
+// Write Admin
+pub fn write_admin(e: &Env, d: Address) {
+    e.storage().set(&DataKey::Admin, &d)
+}
+
+// Read LP Token
+pub fn read_lp_token(e: &Env) -> BytesN<32> {
+    e.storage().get_unchecked(&DataKey::LpToken).unwrap()
+}
+
+// Write LP Token
+pub fn write_lp_token(e: &Env, d: BytesN<32>) {
+    e.storage().set(&DataKey::LpToken, &d)
+}
+
+// Read Total Staked
+pub fn read_total_staked(e: &Env) -> i128 {
+    e.storage()
+        .get(&DataKey::TotalStaked)
+        .unwrap_or(Ok(0))
+        .unwrap()
+}
+
+// Write Total Staked
+pub fn write_total_staked(e: &Env, d: i128) {
+    e.storage().set(&DataKey::TotalStaked, &d)
+}
+
+// Read Rewards
+pub fn read_rewards(e: &Env) -> Vec<RewardInfo> {
+    e.storage()
+        .get(&DataKey::Rewards)
+        .unwrap_or(Ok(vec![e]))
+        .unwrap()
+}
+
+// Write Rewards
+pub fn write_rewards(e: &Env, d: Vec<RewardInfo>) {
+    e.storage().set(&DataKey::Rewards, &d)
+}
+
+// Read Staker
+pub fn read_staker(e: &Env, user: Address) -> StakerInfo {
+    e.storage()
+        .get(&DataKey::Staker(user))
+        .unwrap_or(Ok(StakerInfo {
+            amount: 0,
+            reward_debt: vec![e],
+            pending: vec![e],
+        }))
+        .unwrap()
+}
+
+// Write Staker
+pub fn write_staker(e: &Env, user: Address, d: StakerInfo) {
+    e.storage().set(&DataKey::Staker(user), &d)
+}
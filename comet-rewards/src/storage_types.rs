@@ -0,0 +1,28 @@
+use soroban_sdk::{contracttype, Address, BytesN, Vec};
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RewardInfo {
+    pub token: Address,
+    pub rate: i128,          // reward tokens emitted per second
+    pub acc_per_share: i128, // rewards earned by one staked LP token, scaled by ACC_PRECISION
+    pub last_update: u64,    // ledger timestamp acc_per_share was last brought up to
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct StakerInfo {
+    pub amount: i128,
+    pub reward_debt: Vec<i128>, // amount * acc_per_share at the last update, per reward
+    pub pending: Vec<i128>,     // rewards earned but not claimed yet, per reward
+}
+
+#[derive(Clone)]
+#[contracttype]
+pub enum DataKey {
+    Admin,           // Address allowed to manage the reward tokens
+    LpToken,         // BytesN<32>, contract id of the staked Comet LP token
+    TotalStaked,     // i128
+    Rewards,         // Vec<RewardInfo>
+    Staker(Address), // StakerInfo
+}
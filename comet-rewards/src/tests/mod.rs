@@ -0,0 +1 @@
+pub mod rewards_test;
@@ -0,0 +1,108 @@
+#![cfg(test)]
+
+extern crate std;
+use crate::contract::{CometRewardsContract, CometRewardsContractClient};
+use contracts::testutils::{create_pool, create_token_contract, token};
+use soroban_sdk::testutils::{Address as _, Ledger};
+use soroban_sdk::{vec, Address, Env};
+
+fn to_stroop<T: Into<f64>>(a: T) -> i128 {
+    (a.into() * 1e7) as i128
+}
+
+fn advance_time(e: &Env, seconds: u64) {
+    e.ledger().with_mut(|li| li.timestamp += seconds);
+}
+
+#[test]
+fn test_rewards_full_flow() {
+    let env = Env::default();
+    let admin = Address::random(&env);
+    let user = Address::random(&env);
+    let (pool, _) = create_pool(&env, &admin);
+    let lp_token = token::Client::new(&env, &pool.share_id());
+    lp_token.xfer(&admin, &user, &to_stroop(40));
+
+    let contract_id = env.register_contract(None, CometRewardsContract);
+    let rewards = CometRewardsContractClient::new(&env, &contract_id);
+    let rewards_address = Address::from_contract_id(&env, &contract_id);
+    rewards.init(&admin, &pool.share_id());
+
+    // One reward token per second, funded up front
+    let reward_token = create_token_contract(&env, &admin);
+    reward_token.mint(&admin, &rewards_address, &to_stroop(1000));
+    rewards.add_reward(&admin, &reward_token.address(), &to_stroop(1));
+
+    lp_token.incr_allow(&admin, &rewards_address, &i128::MAX);
+    lp_token.incr_allow(&user, &rewards_address, &i128::MAX);
+    let admin_lp_balance = lp_token.balance(&admin);
+    rewards.deposit(&admin, &to_stroop(40));
+    rewards.deposit(&user, &to_stroop(40));
+    assert_eq!(rewards.get_total_staked(), to_stroop(80));
+    assert_eq!(lp_token.balance(&rewards_address), to_stroop(80));
+
+    // Rewards are split by stake
+    advance_time(&env, 100);
+    assert_eq!(rewards.get_pending(&admin), vec![&env, to_stroop(50)]);
+    assert_eq!(rewards.get_pending(&user), vec![&env, to_stroop(50)]);
+
+    let claimed = rewards.claim(&user);
+    assert_eq!(claimed, vec![&env, to_stroop(50)]);
+    assert_eq!(reward_token.balance(&user), to_stroop(50));
+    assert_eq!(rewards.get_pending(&user), vec![&env, 0]);
+
+    // Once the user leaves, the admin earns the whole emission
+    rewards.withdraw(&user, &to_stroop(40));
+    assert_eq!(lp_token.balance(&user), to_stroop(40));
+    advance_time(&env, 50);
+    assert_eq!(rewards.get_pending(&admin), vec![&env, to_stroop(100)]);
+    assert_eq!(rewards.get_pending(&user), vec![&env, 0]);
+
+    // LP tokens keep working in the pool after being staked
+    pool.exit_pool(&to_stroop(10), &vec![&env, 0, 0, 0], &user);
+    assert_eq!(lp_token.balance(&user), to_stroop(30));
+
+    // Emergency withdraw returns the stake and forfeits the rewards
+    let returned = rewards.emergency_withdraw(&admin);
+    assert_eq!(returned, to_stroop(40));
+    assert_eq!(lp_token.balance(&admin), admin_lp_balance);
+    assert_eq!(rewards.get_pending(&admin), vec![&env, 0]);
+    assert_eq!(rewards.get_total_staked(), 0);
+    assert_eq!(reward_token.balance(&admin), 0);
+}
+
+#[test]
+fn test_rewards_rate_change_and_second_token() {
+    let env = Env::default();
+    let admin = Address::random(&env);
+    let (pool, _) = create_pool(&env, &admin);
+    let lp_token = token::Client::new(&env, &pool.share_id());
+
+    let contract_id = env.register_contract(None, CometRewardsContract);
+    let rewards = CometRewardsContractClient::new(&env, &contract_id);
+    let rewards_address = Address::from_contract_id(&env, &contract_id);
+    rewards.init(&admin, &pool.share_id());
+
+    let reward_a = create_token_contract(&env, &admin);
+    let reward_b = create_token_contract(&env, &admin);
+    reward_a.mint(&admin, &rewards_address, &to_stroop(1000));
+    reward_b.mint(&admin, &rewards_address, &to_stroop(1000));
+    rewards.add_reward(&admin, &reward_a.address(), &to_stroop(1));
+
+    lp_token.incr_allow(&admin, &rewards_address, &i128::MAX);
+    rewards.deposit(&admin, &to_stroop(50));
+    advance_time(&env, 10);
+
+    // The old rate applies up to the change, the new token only from when it's added
+    rewards.set_reward_rate(&admin, &reward_a.address(), &to_stroop(2));
+    rewards.add_reward(&admin, &reward_b.address(), &to_stroop(0.5));
+    advance_time(&env, 10);
+
+    assert_eq!(
+        rewards.get_pending(&admin),
+        vec![&env, to_stroop(30), to_stroop(5)]
+    );
+    rewards.claim(&admin);
+    assert_eq!(reward_a.balance(&admin), to_stroop(30));
+    assert_eq!(reward_b.balance(&admin), to_stroop(5));
+}